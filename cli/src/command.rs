@@ -1,6 +1,9 @@
 use clap::ArgMatches;
 use serde::Serialize;
 use std::fmt::Display;
+use std::sync::atomic::{AtomicI32, Ordering};
+
+static EXIT_CODE: AtomicI32 = AtomicI32::new(0);
 
 pub type CommandResult = Result<Box<dyn Json>, Box<dyn Json>>;
 
@@ -22,3 +25,13 @@ impl<T: Serialize + Display> Json for T {
 pub trait Command {
     fn run(&self, matches: &ArgMatches) -> CommandResult;
 }
+
+/// Makes Smaug exit with `code` once the command is done, so CI can act on
+/// what a report found.
+pub fn set_exit_code(code: i32) {
+    EXIT_CODE.store(code, Ordering::SeqCst);
+}
+
+pub fn exit_code() -> i32 {
+    EXIT_CODE.load(Ordering::SeqCst)
+}
@@ -7,6 +7,10 @@ pub mod dragonruby;
 pub mod init;
 pub mod install;
 pub mod new;
+pub mod outdated;
 pub mod package;
 pub mod publish;
+pub mod remove;
 pub mod run;
+pub mod tree;
+pub mod update;
@@ -5,12 +5,16 @@ use clap::ArgMatches;
 use derive_more::Display;
 use derive_more::Error;
 use log::*;
-use serde::Deserialize;
 use serde::Serialize;
+use smaug_lib::dependency::Dependency;
+use smaug_lib::lockfile::LockedSource;
+use smaug_lib::source::Source;
+use smaug_lib::sources::file_source::FileSource;
+use smaug_lib::sources::url_source::UrlSource;
 use std::env;
 use std::path::Path;
 use std::path::PathBuf;
-use toml_edit::{value, Document};
+use toml_edit::{value, Document, InlineTable, Item};
 use dunce;
 
 pub struct Add;
@@ -25,6 +29,8 @@ pub enum Error {
     Registry,
     #[display(fmt = "Could not install packages.")]
     Install,
+    #[display(fmt = "Could not fetch {}: {}", "package", "reason")]
+    Fetch { package: String, reason: String },
 }
 
 #[derive(Debug, Display, Serialize)]
@@ -55,7 +61,7 @@ impl Command for Add {
         };
 
         let path = Path::new(&canonical);
-        let path = dunce::canonicalize(&path).expect("Could not find path");
+        let path = dunce::canonicalize(path).expect("Could not find path");
 
         let config_path = path.join("Smaug.toml");
 
@@ -66,11 +72,25 @@ impl Command for Add {
         let config =
             std::fs::read_to_string(config_path.clone()).expect("Could not read Smaug.toml");
 
-        let package_name = matches.value_of("PACKAGE").expect("No package given");
-        let latest_version = match fetch_from_registry(package_name.to_string()) {
-            Ok(version) => version,
-            Err(..) => return Err(Box::new(Error::Registry)),
+        let package = matches.value_of("PACKAGE").expect("No package given");
+        let (package_name, latest_version, entry) = if is_archive(package, &path) {
+            match fetch_archive(package, &path) {
+                Ok(archive) => archive,
+                Err(err) => return Err(Box::new(err)),
+            }
+        } else {
+            let latest_version = match smaug_lib::registry::latest_version(package) {
+                Ok(version) => version,
+                Err(..) => return Err(Box::new(Error::Registry)),
+            };
+
+            (
+                package.to_string(),
+                latest_version.clone(),
+                value(latest_version),
+            )
         };
+        let package_name = package_name.as_str();
 
         trace!("Latest version: {}", latest_version);
 
@@ -88,7 +108,7 @@ impl Command for Add {
             }
         }
 
-        doc["dependencies"][package_name] = value(latest_version.clone());
+        doc["dependencies"][package_name] = entry;
 
         std::fs::write(config_path, doc.to_string_in_original_order())
             .expect("Couldn't write config file.");
@@ -104,41 +124,61 @@ impl Command for Add {
     }
 }
 
-#[derive(Debug, Deserialize)]
-struct VersionResponse {
-    version: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct PackageResponse {
-    version: VersionResponse,
-}
-
-fn fetch_from_registry(name: String) -> std::io::Result<String> {
-    let url = format!("https://api.smaug.dev/packages/{}", name);
-    trace!("Fetching latest version from {}", url);
+fn is_archive(package: &str, project_dir: &Path) -> bool {
+    let is_url = package.starts_with("http://") || package.starts_with("https://");
 
-    let response = reqwest::blocking::get(url.as_str());
-
-    match response {
-        Err(..) => Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "couldn't find package",
-        )),
-        Ok(response) => parse_response(response, name),
-    }
+    is_url || project_dir.join(package).is_file()
 }
 
-fn parse_response(response: reqwest::blocking::Response, name: String) -> std::io::Result<String> {
-    if response.status().is_success() {
-        let package_response: PackageResponse =
-            response.json().expect("Couldn't parse registry response");
-
-        Ok(package_response.version.version)
+/// Fetches a zip file or URL so its name and checksum can be written to
+/// Smaug.toml.
+fn fetch_archive(package: &str, project_dir: &Path) -> Result<(String, String, Item), Error> {
+    let fetch_error = |reason: String| Error::Fetch {
+        package: package.to_string(),
+        reason,
+    };
+
+    let source: Box<dyn Source> = if project_dir.join(package).is_file() {
+        Box::new(FileSource {
+            path: PathBuf::from(package),
+            checksum: None,
+        })
     } else {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            format!("Couldn't fetch {} from repository", name),
-        ))
+        Box::new(UrlSource {
+            url: package.to_string(),
+            checksum: None,
+        })
+    };
+
+    let dependency = Dependency {
+        name: package.to_string(),
+        version: "*".to_string(),
+        package: None,
+    };
+    let fetched = source
+        .fetch(&dependency, &project_dir.join("smaug"))
+        .map_err(|err| fetch_error(err.to_string()))?;
+
+    let config_path = fetched.path.join("Smaug.toml");
+    let config = std::fs::read_to_string(&config_path)
+        .ok()
+        .and_then(|contents| smaug_lib::config::from_str(&contents, &config_path).ok())
+        .and_then(|config| config.package)
+        .ok_or_else(|| fetch_error("it doesn't contain a Smaug package".to_string()))?;
+
+    let mut table = InlineTable::default();
+    match fetched.source {
+        LockedSource::File { digest, .. } => {
+            table.get_or_insert("file", package);
+            table.get_or_insert("checksum", smaug_lib::util::digest::checksum(&digest));
+        }
+        LockedSource::Url { digest, .. } => {
+            table.get_or_insert("url", package);
+            table.get_or_insert("checksum", smaug_lib::util::digest::checksum(&digest));
+        }
+        _ => unreachable!(),
     }
+    table.fmt();
+
+    Ok((config.name, config.version, value(table)))
 }
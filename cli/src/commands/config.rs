@@ -1,10 +1,12 @@
 use crate::command::Command;
 use crate::command::CommandResult;
+use crate::commands::install::{format_patch, patches, Patch};
 use clap::ArgMatches;
 use derive_more::Display;
 use derive_more::Error;
 use log::*;
 use serde::Serialize;
+use smaug_lib::config::Error as ConfigError;
 use std::env;
 use std::path::Path;
 use std::path::PathBuf;
@@ -13,9 +15,10 @@ use std::path::PathBuf;
 pub struct Config;
 
 #[derive(Debug, Serialize, Display)]
-#[display(fmt = "{}", "print_config(config)")]
+#[display(fmt = "{}", "print_config(self)")]
 pub struct ConfigResult {
     config: toml::Value,
+    patches: Vec<Patch>,
 }
 
 #[derive(Debug, Display, Error, Serialize)]
@@ -48,13 +51,29 @@ impl Command for Config {
         let contents = std::fs::read_to_string(config_file.clone()).unwrap();
         let loaded = toml::from_str::<toml::Value>(contents.as_str());
 
-        match loaded {
-            Ok(config) => Ok(Box::new(ConfigResult { config })),
-            Err(..) => Err(Box::new(Error::InvalidConfig { path: config_file })),
-        }
+        let config = match loaded {
+            Ok(config) => config,
+            Err(..) => return Err(Box::new(Error::InvalidConfig { path: config_file })),
+        };
+
+        let patches = match smaug_lib::config::load(&config_file) {
+            Ok(loaded) => patches(&loaded),
+            Err(ConfigError::ParseError { path, .. }) => {
+                return Err(Box::new(Error::InvalidConfig { path }))
+            }
+            Err(..) => return Err(Box::new(Error::InvalidConfig { path: config_file })),
+        };
+
+        Ok(Box::new(ConfigResult { config, patches }))
     }
 }
 
-fn print_config(config: &toml::Value) -> String {
-    toml::to_string_pretty(config).expect("Couldn't serialize config")
+fn print_config(result: &ConfigResult) -> String {
+    let mut message = toml::to_string_pretty(&result.config).expect("Couldn't serialize config");
+
+    for patch in result.patches.iter() {
+        message.push_str(format!("\n# {}", format_patch(patch)).as_str());
+    }
+
+    message
 }
@@ -25,6 +25,7 @@ pub enum Error {
 }
 
 impl Command for Docs {
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn run(&self, matches: &ArgMatches) -> CommandResult {
         trace!("Docs Command");
 
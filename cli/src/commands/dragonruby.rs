@@ -15,6 +15,7 @@ use uninstall::Uninstall;
 #[derive(Debug)]
 pub struct DragonRuby;
 
+#[allow(dead_code)]
 trait Result: Display + Serialize {}
 
 impl Command for DragonRuby {
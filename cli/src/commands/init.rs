@@ -35,6 +35,7 @@ enum Error {
 static TEMPLATE: &str = include_str!("../../templates/Project.toml.template");
 
 impl Command for Init {
+    #[allow(clippy::redundant_pattern_matching)]
     fn run(&self, matches: &ArgMatches) -> CommandResult {
         trace!("Init Command");
 
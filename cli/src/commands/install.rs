@@ -1,58 +1,219 @@
+use crate::command;
 use crate::command::Command;
 use crate::command::CommandResult;
+use crate::command::Json;
+use crate::workspace;
 use clap::ArgMatches;
 use derive_more::Display;
 use derive_more::Error;
 use log::*;
 use question::{Answer, Question};
 use resolver::Resolver;
+use semver::VersionReq;
 use serde::Serialize;
-use smaug_lib::{dependency::Dependency, resolver};
-use std::env;
+use smaug_lib::config::Config;
+use smaug_lib::installed;
+use smaug_lib::installed::InstalledFile;
+use smaug_lib::lockfile::LockedPackage;
+use smaug_lib::lockfile::LockedSource;
+use smaug_lib::lockfile::Lockfile;
+use smaug_lib::resolver::Install as FileInstall;
+use smaug_lib::solver::Conflict;
+use smaug_lib::solver::Requirement;
+use smaug_lib::util::digest;
+use smaug_lib::workspace::{Member, Workspace};
+use smaug_lib::{dependency::Dependency, resolver, solver};
+use std::collections::HashMap;
 use std::path::Path;
 use std::path::PathBuf;
 use tinytemplate::TinyTemplate;
-use dunce;
+
+/// The exit code when `install --check` finds a difference.
+const CHECK_EXIT_CODE: i32 = 1;
 
 #[derive(Debug)]
 pub struct Install;
 
 #[derive(Debug, Display, Serialize)]
-#[display(fmt = "Successfully installed your dependencies.")]
+#[display(fmt = "{}", "format_result(self)")]
 pub struct InstallResult {
     dependencies: Vec<Dependency>,
+    pub pruned: Pruned,
+    patches: Vec<Patch>,
+}
+
+/// A dependency whose source is overridden by `[patch]`.
+#[derive(Debug, Serialize)]
+pub struct Patch {
+    pub name: String,
+    pub source: String,
+    pub file: String,
+}
+
+/// Package directories and installed files in the project that no current
+/// dependency owns.
+#[derive(Debug, Default, Serialize)]
+pub struct Pruned {
+    pub removed: bool,
+    pub packages: Vec<String>,
+    pub files: Vec<PathBuf>,
+    pub modified_files: Vec<PathBuf>,
+    /// Directories in `smaug` that Smaug didn't install, which are never
+    /// removed.
+    pub unknown: Vec<String>,
+}
+
+/// The outcome of `smaug install --check`.
+#[derive(Debug, Display, Serialize)]
+#[display(fmt = "{}", "format_differences(differences)")]
+pub struct CheckResult {
+    differences: Vec<MemberDifference>,
+}
+
+/// A difference, along with the workspace member it was found in.
+#[derive(Debug, Serialize)]
+pub struct MemberDifference {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    member: Option<String>,
+    #[serde(flatten)]
+    difference: Difference,
+}
+
+/// A way the project on disk differs from a fresh install of Smaug.toml.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Difference {
+    MissingPackage {
+        package: String,
+    },
+    ExtraPackage {
+        package: String,
+    },
+    StalePackage {
+        package: String,
+        installed: Option<String>,
+        expected: String,
+    },
+    /// Installed at the version Smaug.toml resolves to, but from a different
+    /// source or with different contents.
+    ChangedPackage {
+        package: String,
+        version: String,
+    },
+    MissingFile {
+        package: String,
+        path: PathBuf,
+    },
+    ChangedFile {
+        package: String,
+        path: PathBuf,
+    },
+    OutdatedIndex {
+        path: PathBuf,
+    },
+    OutdatedLockfile {
+        path: PathBuf,
+    },
 }
 
 #[derive(Debug, Display, Error, Serialize)]
 enum Error {
     #[display(fmt = "Failed to install your dependencies.")]
     InstallFailed,
+    #[display(fmt = "Failed to install your dependencies: {}", "reason")]
+    Resolve { reason: String },
+    #[display(fmt = "Failed to resolve your dependencies. {}", "conflict")]
+    Conflict { conflict: Conflict },
+    #[display(fmt = "Failed to resolve your dependencies. {}", "conflict.sources()")]
+    SourceConflict { conflict: Conflict },
     #[display(fmt = "Could not find Smaug.toml at {}", "path.display()")]
     FileNotFound { path: PathBuf },
     #[display(fmt = "Couldn't load Smaug configuration.")]
     Config { path: PathBuf },
+    #[display(fmt = "Couldn't load Smaug.lock at {}", "path.display()")]
+    Lockfile { path: PathBuf },
+    #[display(fmt = "{} is not a valid number of jobs.", "jobs")]
+    Jobs { jobs: String },
+    #[display(fmt = "Could not remove {}", "path.display()")]
+    PruneFailed { path: PathBuf },
+    #[display(
+        fmt = "{} and {} both install {}. Remove one of them from your dependencies.",
+        "first",
+        "second",
+        "path.display()"
+    )]
+    Collision {
+        path: PathBuf,
+        first: String,
+        second: String,
+    },
+    #[display(fmt = "{}", "format_differences(differences)")]
+    Check { differences: Vec<MemberDifference> },
+    #[display(
+        fmt = "Workspace members {} and {} need different versions of {}.",
+        "first",
+        "second",
+        "package"
+    )]
+    WorkspaceConflict {
+        package: String,
+        first: String,
+        second: String,
+    },
+}
+
+#[derive(Debug, Default)]
+pub struct Options {
+    pub jobs: usize,
+    pub force: bool,
+    pub no_prune: bool,
+    /// Leaves writing Smaug.lock to the caller, which shares it between
+    /// workspace members.
+    pub shared_lockfile: bool,
+    /// Registry requirements of every workspace member.
+    pub shared_requirements: Vec<(String, Requirement, VersionReq)>,
+}
+
+impl Options {
+    pub fn from_matches(matches: &ArgMatches) -> Result<Options, Box<dyn Json>> {
+        let jobs = match matches.value_of("jobs") {
+            None => 0,
+            Some(jobs) => match jobs.parse::<usize>() {
+                Ok(jobs) if jobs > 0 => jobs,
+                _ => {
+                    return Err(Box::new(Error::Jobs {
+                        jobs: jobs.to_string(),
+                    }))
+                }
+            },
+        };
+
+        Ok(Options {
+            jobs,
+            force: matches.is_present("force"),
+            no_prune: matches.is_present("no_prune"),
+            shared_lockfile: false,
+            shared_requirements: vec![],
+        })
+    }
 }
 
 impl Command for Install {
     fn run(&self, matches: &ArgMatches) -> CommandResult {
         trace!("Install Command");
 
-        let current_directory = env::current_dir().unwrap();
-        let directory: &str = matches
-            .value_of("path")
-            .unwrap_or_else(|| current_directory.to_str().unwrap());
-        debug!("Directory: {}", directory);
-        let canonical = match dunce::canonicalize(directory) {
-            Ok(dir) => dir,
-            Err(..) => {
-                return Err(Box::new(Error::FileNotFound {
-                    path: Path::new(directory).to_path_buf(),
-                }))
-            }
+        let selection = match workspace::select(matches.value_of("path")) {
+            Ok(selection) => selection,
+            Err(path) => return Err(Box::new(Error::FileNotFound { path })),
         };
-        let path = Path::new(&canonical);
-        let path = dunce::canonicalize(&path).expect("Could not find path");
+        let options = Options::from_matches(matches)?;
+        let check_only = matches.is_present("check");
+
+        if let Some(workspace) = selection.workspace {
+            return install_workspace(&workspace, &selection.members, options, check_only);
+        }
 
+        let path = selection.members[0].path.clone();
         let config_path = path.join("Smaug.toml");
 
         let config = match smaug_lib::config::load(&config_path) {
@@ -61,46 +222,644 @@ impl Command for Install {
         };
         debug!("Smaug config: {:?}", config);
 
-        let mut registry = resolver::new_from_config(&config);
+        let lock_path = path.join("Smaug.lock");
+        let lockfile = match smaug_lib::lockfile::load(&lock_path) {
+            Ok(lockfile) => lockfile,
+            Err(..) => return Err(Box::new(Error::Lockfile { path: lock_path })),
+        };
+
+        if check_only {
+            let (differences, resolved) = check(&path, &config, &lockfile, &options)?;
+            let mut differences: Vec<MemberDifference> = differences
+                .into_iter()
+                .map(|difference| MemberDifference {
+                    member: None,
+                    difference,
+                })
+                .collect();
+
+            let resolved = lockable(std::slice::from_ref(&config), &resolved, &lockfile);
+            if lockfile_differs(&resolved, &lockfile) {
+                differences.push(MemberDifference {
+                    member: None,
+                    difference: Difference::OutdatedLockfile {
+                        path: PathBuf::from("Smaug.lock"),
+                    },
+                });
+            }
+
+            return check_result(differences);
+        }
+
+        let (registry, dependencies, pruned) = install(&path, &config, &lockfile, &options)?;
+        let patches = active_patches(&config, &registry.lockfile);
+
+        Ok(Box::new(InstallResult {
+            dependencies,
+            pruned,
+            patches,
+        }))
+    }
+}
+
+fn check_result(differences: Vec<MemberDifference>) -> CommandResult {
+    if differences.is_empty() {
+        Ok(Box::new(CheckResult { differences }))
+    } else {
+        command::set_exit_code(CHECK_EXIT_CODE);
+        Err(Box::new(Error::Check { differences }))
+    }
+}
+
+fn active_patches(config: &Config, lockfile: &Lockfile) -> Vec<Patch> {
+    patches(config)
+        .into_iter()
+        .filter(|patch| lockfile.get(&patch.name).is_some())
+        .collect()
+}
+
+/// Installs or checks workspace members against the workspace's shared
+/// Smaug.lock.
+fn install_workspace(
+    workspace: &Workspace,
+    members: &[Member],
+    options: Options,
+    check_only: bool,
+) -> CommandResult {
+    let lock_path = workspace.lock_path();
+    let shared = match smaug_lib::lockfile::load(&lock_path) {
+        Ok(lockfile) => lockfile,
+        Err(..) => return Err(Box::new(Error::Lockfile { path: lock_path })),
+    };
+
+    if !check_only {
+        let (_, result) = install_members(workspace, members, &shared, &shared, options)?;
+
+        return Ok(Box::new(result));
+    }
+
+    let (merged, _, mut differences) =
+        resolve_members(workspace, members, &shared, &shared, options, true)?;
+
+    if lockfile_differs(&merged, &shared) {
+        differences.push(MemberDifference {
+            member: None,
+            difference: Difference::OutdatedLockfile {
+                path: PathBuf::from("Smaug.lock"),
+            },
+        });
+    }
+
+    check_result(differences)
+}
+
+/// Installs workspace members against `locked` and writes the workspace's
+/// Smaug.lock. Packages that only the other members need stay locked as they
+/// are in `shared`.
+pub fn install_members(
+    workspace: &Workspace,
+    members: &[Member],
+    shared: &Lockfile,
+    locked: &Lockfile,
+    options: Options,
+) -> Result<(Lockfile, InstallResult), Box<dyn Json>> {
+    let (merged, result, _) = resolve_members(workspace, members, shared, locked, options, false)?;
+
+    let lock_path = workspace.lock_path();
+    if merged.write(&lock_path).is_err() {
+        return Err(Box::new(Error::Lockfile { path: lock_path }));
+    }
+
+    Ok((merged, result))
+}
+
+/// Installs or checks the games among the workspace members one after
+/// another. Each member is solved with the registry requirements of every
+/// member and prefers the versions the members before it chose, so they all
+/// get the same versions. Packages are installed by the games that use them.
+fn resolve_members(
+    workspace: &Workspace,
+    members: &[Member],
+    shared: &Lockfile,
+    locked: &Lockfile,
+    options: Options,
+    check_only: bool,
+) -> Result<(Lockfile, InstallResult, Vec<MemberDifference>), Box<dyn Json>> {
+    let members = workspace::projects(members);
+    let mut shared_requirements = vec![];
+    let mut other_roots = vec![];
+    for member in workspace.members.iter() {
+        let config = member_config(workspace, member)?;
+        let resolver = resolver::new_from_config(&config);
+
+        match resolver.registry_requirements() {
+            Ok(requirements) => shared_requirements.extend(requirements),
+            Err(err) => {
+                return Err(Box::new(Error::Resolve {
+                    reason: err.to_string(),
+                }))
+            }
+        }
+
+        if !members.contains(member) {
+            other_roots.extend(dependency_names(&resolver));
+        }
+    }
+
+    let options = Options {
+        shared_lockfile: true,
+        shared_requirements,
+        ..options
+    };
+
+    let mut merged = Lockfile::default();
+    let mut configs = vec![];
+    let mut owners = HashMap::new();
+    let mut differences = vec![];
+    let mut result = InstallResult {
+        dependencies: vec![],
+        pruned: Pruned {
+            removed: !options.no_prune,
+            ..Pruned::default()
+        },
+        patches: vec![],
+    };
+
+    for member in members.iter() {
+        info!("Workspace member {}", member.name);
+        let config = member_config(workspace, member)?;
+
+        let mut member_locked = locked.clone();
+        for package in merged.packages.iter() {
+            member_locked
+                .packages
+                .retain(|other| other.name != package.name);
+            member_locked.packages.push(package.clone());
+        }
+        let member_locked = workspace.member_lockfile(member, &member_locked);
+
+        let resolved = if check_only {
+            let (found, resolved) = check(&member.path, &config, &member_locked, &options)?;
+            differences.extend(found.into_iter().map(|difference| MemberDifference {
+                member: Some(member.name.clone()),
+                difference,
+            }));
 
-        match registry.install(path.join("smaug")) {
-            Ok(dependencies) => {
-                debug!("{:?}", registry.requires);
-                if install_files(&registry).is_err() {
-                    return Err(Box::new(Error::InstallFailed));
+            resolved
+        } else {
+            let (registry, dependencies, pruned) =
+                install(&member.path, &config, &member_locked, &options)?;
+            result.dependencies.extend(dependencies);
+            result.pruned.packages.extend(pruned.packages);
+            result.pruned.files.extend(pruned.files);
+            result.pruned.modified_files.extend(pruned.modified_files);
+            result.pruned.unknown.extend(pruned.unknown);
+            for patch in active_patches(&config, &registry.lockfile) {
+                if !result.patches.iter().any(|other| other.name == patch.name) {
+                    result.patches.push(patch);
                 }
+            }
+
+            registry.lockfile
+        };
+        configs.push(config);
+
+        let resolved = workspace.root_lockfile(member, &resolved);
+        merge_lockfile(&mut merged, &mut owners, member, resolved, &workspace.root)?;
+    }
+
+    let mut merged = lockable(&configs, &merged, locked);
+
+    // Packages of members that weren't installed this time stay locked.
+    let kept = reachable(shared, &other_roots);
+    for package in shared.packages.iter() {
+        if kept.contains(&package.name) && merged.get(&package.name).is_none() {
+            merged.packages.push(package.clone());
+        }
+    }
+    // The same packages are written in the same order, whichever members
+    // were installed.
+    merged
+        .packages
+        .sort_by(|first, second| first.name.cmp(&second.name));
+
+    Ok((merged, result, differences))
+}
+
+/// Names of the dependencies a project declares, including dev dependencies.
+pub fn dependency_names(resolver: &Resolver) -> Vec<String> {
+    resolver
+        .requirements
+        .iter()
+        .chain(resolver.dev_requirements.iter())
+        .map(|dependency| dependency.name.clone())
+        .collect()
+}
+
+/// Names of the locked packages that `roots` depend on, directly or not.
+pub fn reachable(lockfile: &Lockfile, roots: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    let mut pending = roots.to_vec();
+
+    while let Some(name) = pending.pop() {
+        if names.contains(&name) {
+            continue;
+        }
+
+        if let Some(package) = lockfile.get(&name) {
+            pending.extend(package.dependencies.iter().cloned());
+            names.push(name);
+        }
+    }
+
+    names
+}
+
+/// Loads a member's configuration with the workspace's patches applied.
+fn member_config(workspace: &Workspace, member: &Member) -> Result<Config, Box<dyn Json>> {
+    let config_path = member.path.join("Smaug.toml");
+    let mut config = match smaug_lib::config::load(&config_path) {
+        Ok(config) => config,
+        Err(..) => return Err(Box::new(Error::Config { path: config_path })),
+    };
+
+    for (name, options) in workspace.member_patch(member, &workspace.patch) {
+        if !config.patch.contains_key(&name) {
+            config.patch.insert(name, options);
+        }
+    }
+
+    for (name, options) in workspace.member_patch(member, &workspace.local_patch) {
+        if !config.local_patch.contains_key(&name) {
+            config.local_patch.insert(name, options);
+        }
+    }
+
+    Ok(config)
+}
+
+/// Adds a member's resolved packages to the workspace lockfile. Members must
+/// agree on the version and source of every package they share.
+fn merge_lockfile(
+    merged: &mut Lockfile,
+    owners: &mut HashMap<String, Member>,
+    member: &Member,
+    resolved: Lockfile,
+    root: &Path,
+) -> Result<(), Box<dyn Json>> {
+    for package in resolved.packages {
+        let existing = match merged
+            .packages
+            .iter_mut()
+            .find(|existing| existing.name == package.name)
+        {
+            Some(existing) => existing,
+            None => {
+                owners.insert(package.name.clone(), member.clone());
+                merged.packages.push(package);
+                continue;
+            }
+        };
+        let owner = &owners[&package.name];
+
+        if existing.version != package.version
+            || !same_locked_source(&existing.source, &package.source, root)
+        {
+            return Err(Box::new(Error::WorkspaceConflict {
+                package: package.name.clone(),
+                first: owner.name.clone(),
+                second: member.name.clone(),
+            }));
+        }
 
-                write_index(&registry, &path);
+        existing.dev = existing.dev && package.dev;
+    }
+
+    Ok(())
+}
+
+/// Compares the sources two members locked, reading directories from the
+/// workspace root and zip files by their contents.
+fn same_locked_source(first: &LockedSource, second: &LockedSource, root: &Path) -> bool {
+    match (first, second) {
+        (LockedSource::Dir { dir: first, .. }, LockedSource::Dir { dir: second, .. }) => {
+            let first = root.join(first);
+            let second = root.join(second);
+
+            dunce::canonicalize(&first).unwrap_or(first)
+                == dunce::canonicalize(&second).unwrap_or(second)
+        }
+        (LockedSource::File { digest: first, .. }, LockedSource::File { digest: second, .. }) => {
+            first == second
+        }
+        _ => first == second,
+    }
+}
+
+/// The Smaug.lock to write for the projects in `configs`. Packages patched in
+/// the untracked Smaug.local.toml keep the entry they were locked with
+/// before, or are left out, so local patches never reach the committed
+/// lockfile.
+pub fn lockable(configs: &[Config], resolved: &Lockfile, locked: &Lockfile) -> Lockfile {
+    let locally_patched = |name: &String| {
+        configs
+            .iter()
+            .any(|config| config.local_patch.contains_key(name))
+    };
+
+    if !resolved
+        .packages
+        .iter()
+        .any(|package| locally_patched(&package.name))
+    {
+        return resolved.clone();
+    }
+
+    let mut packages: Vec<LockedPackage> = resolved
+        .packages
+        .iter()
+        .filter_map(|package| {
+            if locally_patched(&package.name) {
+                locked.get(&package.name).cloned()
+            } else {
+                Some(package.clone())
+            }
+        })
+        .collect();
+
+    for package in locked.packages.iter() {
+        if !packages.iter().any(|other| other.name == package.name) {
+            packages.push(package.clone());
+        }
+    }
+
+    // Drop what only the local copies need, and keep what the locked ones do.
+    let mut lockable = Lockfile { packages };
+    let roots: Vec<String> = configs
+        .iter()
+        .flat_map(|config| dependency_names(&resolver::new_from_config(config)))
+        .collect();
+    let names = reachable(&lockable, &roots);
+    lockable
+        .packages
+        .retain(|package| names.contains(&package.name));
+
+    lockable
+}
+
+fn lockfile_differs(resolved: &Lockfile, locked: &Lockfile) -> bool {
+    resolved.packages.len() != locked.packages.len()
+        || !resolved
+            .packages
+            .iter()
+            .all(|package| locked.get(&package.name) == Some(package))
+}
+
+pub fn install(
+    path: &Path,
+    config: &Config,
+    lockfile: &Lockfile,
+    options: &Options,
+) -> Result<(Resolver, Vec<Dependency>, Pruned), Box<dyn Json>> {
+    let mut registry = resolver::new_from_config(config);
+    registry.use_lockfile(lockfile);
+    registry.jobs = options.jobs;
+    registry.force = options.force;
+    registry.shared_requirements = options.shared_requirements.clone();
+
+    match registry.install(path.join("smaug")) {
+        Ok(dependencies) => {
+            debug!("{:?}", registry.requires);
+            install_files(&registry, path)?;
+
+            write_index(&registry, path);
 
-                Ok(Box::new(InstallResult { dependencies }))
+            let lock_path = path.join("Smaug.lock");
+            if !options.shared_lockfile
+                && lockable(std::slice::from_ref(config), &registry.lockfile, lockfile)
+                    .write(&lock_path)
+                    .is_err()
+            {
+                return Err(Box::new(Error::Lockfile { path: lock_path }));
             }
-            Err(..) => Err(Box::new(Error::InstallFailed)),
+
+            let pruned = prune(&registry, path, !options.no_prune)?;
+
+            Ok((registry, dependencies, pruned))
+        }
+        Err(solver::Error::Conflict(conflict)) => Err(Box::new(Error::Conflict { conflict })),
+        Err(solver::Error::SourceConflict(conflict)) => {
+            Err(Box::new(Error::SourceConflict { conflict }))
         }
+        Err(solver::Error::Io(err)) => Err(Box::new(Error::Resolve {
+            reason: err.to_string(),
+        })),
     }
 }
 
+/// Resolves Smaug.toml into a scratch packages directory and lists how the
+/// installed packages differ from it, without changing them.
+pub fn check(
+    path: &Path,
+    config: &Config,
+    lockfile: &Lockfile,
+    options: &Options,
+) -> Result<(Vec<Difference>, Lockfile), Box<dyn Json>> {
+    // Sources read relative paths from the directory the packages are
+    // installed into, so the scratch copy has to sit in the project.
+    let staging = path.join(format!(".smaug-check-{}", std::process::id()));
+    let remove_staging = || rm_rf::ensure_removed(&staging).is_ok();
+
+    if !remove_staging() {
+        return Err(Box::new(Error::PruneFailed { path: staging }));
+    }
+
+    let mut registry = resolver::new_from_config(config);
+    registry.use_lockfile(lockfile);
+    registry.jobs = options.jobs;
+    registry.shared_requirements = options.shared_requirements.clone();
+
+    let resolved = registry.install(staging.clone());
+    let differences = match resolved {
+        Ok(..) => match check_collisions(&registry, path) {
+            Ok(()) => Ok((
+                compare(&registry, &staging, path),
+                registry.lockfile.clone(),
+            )),
+            Err(err) => Err(err),
+        },
+        Err(solver::Error::Conflict(conflict)) => {
+            Err(Box::new(Error::Conflict { conflict }) as Box<dyn Json>)
+        }
+        Err(solver::Error::SourceConflict(conflict)) => {
+            Err(Box::new(Error::SourceConflict { conflict }) as Box<dyn Json>)
+        }
+        Err(solver::Error::Io(err)) => Err(Box::new(Error::Resolve {
+            reason: err.to_string(),
+        }) as Box<dyn Json>),
+    };
+
+    remove_staging();
+
+    differences
+}
+
+fn compare(resolver: &Resolver, staging: &Path, path: &Path) -> Vec<Difference> {
+    let smaug_dir = path.join("smaug");
+    let mut differences = vec![];
+
+    for package in resolver.lockfile.packages.iter() {
+        if !smaug_dir.join(&package.name).is_dir() {
+            differences.push(Difference::MissingPackage {
+                package: package.name.clone(),
+            });
+            continue;
+        }
+
+        let record = installed::load(&smaug_dir, &package.name);
+
+        let staged = installed::load(staging, &package.name);
+
+        match record {
+            Some(record) if record.version == package.version => {
+                if !record.matches(package)
+                    || record.digest != staged.and_then(|staged| staged.digest)
+                {
+                    differences.push(Difference::ChangedPackage {
+                        package: package.name.clone(),
+                        version: package.version.clone(),
+                    });
+                }
+            }
+            _ => differences.push(Difference::StalePackage {
+                package: package.name.clone(),
+                installed: record.map(|record| record.version),
+                expected: package.version.clone(),
+            }),
+        }
+    }
+
+    if let Ok(entries) = std::fs::read_dir(&smaug_dir) {
+        let mut extra: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .filter(|name| !name.starts_with('.'))
+            .filter(|name| resolver.lockfile.get(name).is_none())
+            .filter(|name| installed::path(&smaug_dir, name).is_file())
+            .collect();
+        extra.sort();
+
+        differences.extend(
+            extra
+                .into_iter()
+                .map(|package| Difference::ExtraPackage { package }),
+        );
+    }
+
+    for install in resolver.installs.iter() {
+        let relative = install.to.strip_prefix(path).unwrap_or(&install.to);
+        let destination = path.join(relative);
+        let expected = digest::file(&install.from).ok();
+
+        if !destination.is_file() {
+            differences.push(Difference::MissingFile {
+                package: install.package.clone(),
+                path: relative.to_path_buf(),
+            });
+        } else if digest::file(&destination).ok() != expected {
+            differences.push(Difference::ChangedFile {
+                package: install.package.clone(),
+                path: relative.to_path_buf(),
+            });
+        }
+    }
+
+    let index = std::fs::read_to_string(path.join("smaug.rb")).ok();
+    if index.as_deref() != Some(project_index(resolver).as_str()) {
+        differences.push(Difference::OutdatedIndex {
+            path: PathBuf::from("smaug.rb"),
+        });
+    }
+
+    differences
+}
+
 #[derive(Debug, Serialize)]
 struct Index {
+    dev: bool,
     requires: Vec<String>,
 }
 
 static INDEX_TEMPLATE: &str = include_str!("../../templates/smaug.rb.template");
-fn write_index(resolver: &Resolver, path: &Path) {
-    trace!("Writing index");
+fn render_index(requires: Vec<String>, dev: bool) -> String {
     let mut tt = TinyTemplate::new();
 
     tt.add_template("smaug.rb", INDEX_TEMPLATE)
         .expect("couldn't add template.");
 
-    let context = Index {
-        requires: resolver.requires.clone(),
-    };
+    let context = Index { dev, requires };
 
     debug!("Context: {:?}", context);
 
-    let rendered = tt
-        .render("smaug.rb", &context)
-        .expect("Could not render smaug.rb");
+    tt.render("smaug.rb", &context)
+        .expect("Could not render smaug.rb")
+}
+
+/// Renders `smaug.rb` for the project as installed, dev-dependencies included.
+fn project_index(resolver: &Resolver) -> String {
+    let mut requires = resolver.requires.clone();
+    requires.extend(resolver.dev_requires.iter().cloned());
+    let dev = resolver.lockfile.packages.iter().any(|package| package.dev);
+
+    render_index(requires, dev)
+}
+
+/// Removes the packages and installed files that only dev-dependencies need
+/// from a copy of the project, and rewrites its `smaug.rb` without them.
+pub fn strip_dev_dependencies(path: &Path, build_dir: &Path) -> std::io::Result<()> {
+    let dev: Vec<installed::InstalledPackage> = installed::all(&path.join("smaug"))
+        .into_iter()
+        .filter(|record| record.dev)
+        .collect();
+
+    if dev.is_empty() {
+        return Ok(());
+    }
+
+    let build_smaug_dir = build_dir.join("smaug");
+    let mut dev_requires = vec![];
+
+    for record in dev {
+        trace!("Leaving dev-dependency {} out of the build", record.name);
+        for file in record.files.iter() {
+            let file = build_dir.join(&file.path);
+
+            if file.is_file() {
+                std::fs::remove_file(file)?;
+            }
+        }
+
+        rm_rf::ensure_removed(build_smaug_dir.join(&record.name))
+            .map_err(|_| std::io::Error::other(format!("Couldn't remove {}", record.name)))?;
+        installed::remove(&build_smaug_dir, &record.name)?;
+        dev_requires.extend(record.requires);
+    }
+
+    let index_path = build_dir.join("smaug.rb");
+    let requires = std::fs::read_to_string(&index_path)?
+        .lines()
+        .filter_map(|line| line.strip_prefix("require \"")?.strip_suffix('"'))
+        .filter(|require| !dev_requires.iter().any(|dev| dev == require))
+        .map(str::to_string)
+        .collect();
+
+    std::fs::write(index_path, render_index(requires, false))
+}
+
+fn write_index(resolver: &Resolver, path: &Path) {
+    trace!("Writing index");
+    let rendered = project_index(resolver);
 
     let index_path = path.join("smaug.rb");
     trace!("Writing index to {}", index_path.display());
@@ -109,40 +868,127 @@ fn write_index(resolver: &Resolver, path: &Path) {
     info!("Add `require \"smaug.rb\" to the top of your main.rb");
 }
 
-fn install_files(resolver: &Resolver) -> std::io::Result<()> {
+fn install_files(resolver: &Resolver, path: &Path) -> Result<(), Box<dyn Json>> {
     trace!("Installing files");
     debug!("{:?}", resolver.installs);
-    for install in resolver.installs.iter() {
-        let source = install.from.as_path();
-        let destination = install.to.as_path();
+    check_collisions(resolver, path)?;
+
+    let smaug_dir = path.join("smaug");
+    let failed = |_| -> Box<dyn Json> { Box::new(Error::InstallFailed) };
+
+    for package in resolver.lockfile.packages.iter() {
+        let mut record = match installed::load(&smaug_dir, &package.name) {
+            Some(record) => record,
+            None => continue,
+        };
+        let installs: Vec<&FileInstall> = resolver
+            .installs
+            .iter()
+            .filter(|install| install.package == package.name)
+            .collect();
+
+        for file in record.files.iter() {
+            let to = path.join(&file.path);
+            let claimed = resolver.installs.iter().any(|install| install.to == to);
+
+            if claimed || !to.is_file() {
+                continue;
+            }
+
+            if is_unmodified(&to, &file.digest) {
+                info!(
+                    "Removing {}, which {} no longer installs",
+                    to.display(),
+                    package.name
+                );
+                std::fs::remove_file(&to).map_err(failed)?;
+            } else {
+                info!(
+                    "{} is no longer installed by {} but has local changes, so it was kept.",
+                    to.display(),
+                    package.name
+                );
+            }
+        }
+
+        let mut files = vec![];
+
+        for install in installs {
+            let source = install.from.as_path();
+            let destination = install.to.as_path();
+            let previous = record
+                .files
+                .iter()
+                .find(|file| path.join(&file.path) == destination);
+            let source_digest = digest::file(source).map_err(failed)?;
+
+            let digest = if can_install_file(source, destination, previous) {
+                trace!(
+                    "Copying file from {} to {}",
+                    source.display(),
+                    destination.display()
+                );
+                std::fs::create_dir_all(destination.parent().unwrap()).map_err(failed)?;
+                std::fs::copy(source, destination).map_err(failed)?;
+
+                source_digest
+            } else {
+                previous.map_or(source_digest, |file| file.digest.clone())
+            };
+
+            files.push(InstalledFile {
+                path: destination
+                    .strip_prefix(path)
+                    .unwrap_or(destination)
+                    .to_path_buf(),
+                digest,
+            });
+        }
+
+        record.files = files;
+        record.write(&smaug_dir).map_err(failed)?;
+    }
+
+    Ok(())
+}
+
+/// Refuses to install when two packages would write the same file.
+fn check_collisions(resolver: &Resolver, path: &Path) -> Result<(), Box<dyn Json>> {
+    for (index, install) in resolver.installs.iter().enumerate() {
+        let other = resolver.installs[..index]
+            .iter()
+            .find(|other| other.to == install.to && other.package != install.package);
 
-        if can_install_file(source, destination) {
-            trace!(
-                "Copying file from {} to {}",
-                source.display(),
-                destination.display()
-            );
-            std::fs::create_dir_all(destination.parent().unwrap())?;
-            std::fs::copy(source, destination)?;
+        if let Some(other) = other {
+            return Err(Box::new(Error::Collision {
+                path: install
+                    .to
+                    .strip_prefix(path)
+                    .unwrap_or(&install.to)
+                    .to_path_buf(),
+                first: other.package.clone(),
+                second: install.package.clone(),
+            }));
         }
     }
 
     Ok(())
 }
 
-fn can_install_file(source: &Path, destination: &Path) -> bool {
+fn can_install_file(source: &Path, destination: &Path, previous: Option<&InstalledFile>) -> bool {
     if !destination.exists() {
         return true;
     }
 
-    let source_digest = smaug_lib::util::digest::file(source).unwrap();
-    let destination_digest = smaug_lib::util::digest::file(destination).unwrap();
+    let source_digest = digest::file(source).unwrap();
+    let destination_digest = digest::file(destination).unwrap();
     debug!(
         "Source: {}, Destination: {}",
         source_digest, destination_digest
     );
 
-    let changed = source_digest != destination_digest;
+    let changed = source_digest != destination_digest
+        && previous.is_none_or(|file| file.digest != destination_digest);
 
     if !changed {
         return true;
@@ -160,3 +1006,258 @@ fn can_install_file(source: &Path, destination: &Path) -> bool {
 
     answer == Answer::YES
 }
+
+/// Finds package directories in `smaug/` that no dependency resolved to, along
+/// with the files they installed, and removes them unless `remove` is false.
+/// Installed files with local changes are always kept.
+fn prune(resolver: &Resolver, path: &Path, remove: bool) -> Result<Pruned, Box<dyn Json>> {
+    let smaug_dir = path.join("smaug");
+    let mut pruned = Pruned {
+        removed: remove,
+        ..Pruned::default()
+    };
+
+    let entries = match std::fs::read_dir(&smaug_dir) {
+        Ok(entries) => entries,
+        Err(..) => return Ok(pruned),
+    };
+
+    let mut orphans: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .filter(|name| !name.starts_with('.'))
+        .filter(|name| resolver.lockfile.get(name).is_none())
+        .collect();
+    orphans.sort();
+
+    for name in orphans {
+        if !installed::path(&smaug_dir, &name).is_file() {
+            debug!("smaug/{} wasn't installed by Smaug", name);
+            pruned.unknown.push(name);
+            continue;
+        }
+
+        let package_dir = smaug_dir.join(&name);
+
+        let files = installed::load(&smaug_dir, &name)
+            .map(|record| record.files)
+            .unwrap_or_default();
+
+        for file in files {
+            let to = path.join(&file.path);
+            let claimed = resolver.installs.iter().any(|install| install.to == to);
+
+            if claimed || !to.is_file() {
+                continue;
+            }
+
+            if !is_unmodified(&to, &file.digest) {
+                pruned.modified_files.push(to);
+                continue;
+            }
+
+            if remove {
+                trace!("Removing installed file {}", to.display());
+                if std::fs::remove_file(&to).is_err() {
+                    return Err(Box::new(Error::PruneFailed { path: to }));
+                }
+            }
+            pruned.files.push(to);
+        }
+
+        if remove {
+            trace!("Removing package directory {}", package_dir.display());
+            if smaug_lib::util::dir::remove(&package_dir).is_err() {
+                return Err(Box::new(Error::PruneFailed { path: package_dir }));
+            }
+
+            if installed::remove(&smaug_dir, &name).is_err() {
+                return Err(Box::new(Error::PruneFailed {
+                    path: installed::path(&smaug_dir, &name),
+                }));
+            }
+        }
+        pruned.packages.push(name);
+    }
+
+    Ok(pruned)
+}
+
+fn is_unmodified(path: &Path, installed_digest: &str) -> bool {
+    digest::file(path).is_ok_and(|digest| digest == installed_digest)
+}
+
+fn format_differences(differences: &[MemberDifference]) -> String {
+    if differences.is_empty() {
+        return "Your project matches Smaug.toml.".to_string();
+    }
+
+    let lines: Vec<String> = differences
+        .iter()
+        .map(|found| match &found.member {
+            Some(member) => format!("* {}: {}", member, format_difference(&found.difference)),
+            None => format!("* {}", format_difference(&found.difference)),
+        })
+        .collect();
+
+    format!(
+        "Your project doesn't match Smaug.toml. Run `smaug install` to fix it.\n{}",
+        lines.join("\n")
+    )
+}
+
+fn format_difference(difference: &Difference) -> String {
+    match difference {
+        Difference::MissingPackage { package } => format!("{} is not installed", package),
+        Difference::ExtraPackage { package } => {
+            format!("{} is installed but no dependency uses it", package)
+        }
+        Difference::StalePackage {
+            package,
+            installed,
+            expected,
+        } => format!(
+            "{} {} is out of date, Smaug.toml resolves to {}",
+            package,
+            installed.as_deref().unwrap_or("(unknown)"),
+            expected
+        ),
+        Difference::ChangedPackage { package, version } => {
+            format!("{} {} has changed since it was installed", package, version)
+        }
+        Difference::MissingFile { package, path } => {
+            format!("{} from {} is missing", path.display(), package)
+        }
+        Difference::ChangedFile { package, path } => {
+            format!("{} differs from the copy in {}", path.display(), package)
+        }
+        Difference::OutdatedIndex { path } => format!("{} is out of date", path.display()),
+        Difference::OutdatedLockfile { path } => {
+            format!("{} is out of date", path.display())
+        }
+    }
+}
+
+/// Lists the patches in Smaug.toml and Smaug.local.toml.
+pub fn patches(config: &Config) -> Vec<Patch> {
+    config
+        .patches()
+        .into_iter()
+        .map(|(name, options)| Patch {
+            file: if config.local_patch.contains_key(&name) {
+                "Smaug.local.toml".to_string()
+            } else {
+                "Smaug.toml".to_string()
+            },
+            source: format!("{}", options),
+            name,
+        })
+        .collect()
+}
+
+pub fn format_patch(patch: &Patch) -> String {
+    format!(
+        "{} is patched to use {} by {}.",
+        patch.name, patch.source, patch.file
+    )
+}
+
+fn format_result(result: &InstallResult) -> String {
+    let mut message = "Successfully installed your dependencies.".to_string();
+
+    for patch in result.patches.iter() {
+        message.push_str(format!("\n{}", format_patch(patch)).as_str());
+    }
+
+    message.push_str(format_pruned(&result.pruned).as_str());
+
+    message
+}
+
+pub fn format_pruned(pruned: &Pruned) -> String {
+    let mut message = String::new();
+
+    for package in pruned.packages.iter() {
+        if pruned.removed {
+            message.push_str(format!("\nRemoved unused package {}.", package).as_str());
+        } else {
+            message.push_str(format!("\nsmaug/{} is no longer used.", package).as_str());
+        }
+    }
+
+    for file in pruned.files.iter() {
+        if !pruned.removed {
+            message.push_str(format!("\n{} is no longer used.", file.display()).as_str());
+        }
+    }
+
+    for file in pruned.modified_files.iter() {
+        message.push_str(
+            format!(
+                "\n{} is no longer used but has local changes, so it was kept.",
+                file.display()
+            )
+            .as_str(),
+        );
+    }
+
+    for name in pruned.unknown.iter() {
+        message.push_str(
+            format!(
+                "\nsmaug/{} wasn't installed by Smaug, so it was kept.",
+                name
+            )
+            .as_str(),
+        );
+    }
+
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_installed_files_relative_to_the_project() {
+        let path = std::env::temp_dir().join(format!("smaug-compare-{}", std::process::id()));
+        let staging = path.join(".smaug-check");
+        let from = staging.join("tween").join("lib");
+        std::fs::create_dir_all(&from).unwrap();
+        std::fs::create_dir_all(path.join("app")).unwrap();
+        std::fs::write(from.join("tween.rb"), "new").unwrap();
+        std::fs::write(from.join("ease.rb"), "new").unwrap();
+        std::fs::write(path.join("app").join("tween.rb"), "old").unwrap();
+
+        let mut resolver = Resolver::default();
+        for name in ["tween.rb", "ease.rb"].iter() {
+            resolver.installs.push(FileInstall {
+                package: "tween".to_string(),
+                from: from.join(name),
+                to: path.join("app").join(name),
+            });
+        }
+
+        let messages: Vec<String> = compare(&resolver, &staging, &path)
+            .iter()
+            .map(format_difference)
+            .collect();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(
+            messages,
+            vec![
+                format!(
+                    "{} differs from the copy in tween",
+                    Path::new("app").join("tween.rb").display()
+                ),
+                format!(
+                    "{} from tween is missing",
+                    Path::new("app").join("ease.rb").display()
+                ),
+                "smaug.rb is out of date".to_string(),
+            ]
+        );
+    }
+}
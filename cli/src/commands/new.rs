@@ -25,6 +25,7 @@ pub struct NewResult {
 }
 
 impl Command for New {
+    #[allow(clippy::redundant_pattern_matching)]
     fn run(&self, matches: &ArgMatches) -> CommandResult {
         trace!("New Command");
 
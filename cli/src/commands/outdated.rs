@@ -0,0 +1,234 @@
+use crate::command;
+use crate::command::Command;
+use crate::command::CommandResult;
+use crate::workspace;
+use clap::ArgMatches;
+use derive_more::Display;
+use derive_more::Error;
+use log::*;
+use semver::Version;
+use semver::VersionReq;
+use serde::Serialize;
+use smaug_lib::config::DependencyOptions;
+use smaug_lib::lockfile::{LockedPackage, LockedSource};
+use smaug_lib::sources::git_source::GitSource;
+use std::path::PathBuf;
+
+/// The exit code when a dependency is outdated. `install --check` uses 1 and
+/// invalid arguments exit with 2.
+const OUTDATED_EXIT_CODE: i32 = 3;
+
+#[derive(Debug)]
+pub struct Outdated;
+
+#[derive(Debug, Serialize)]
+pub struct OutdatedPackage {
+    name: String,
+    source: String,
+    current: String,
+    allowed: String,
+    latest: String,
+    outdated: bool,
+}
+
+#[derive(Debug, Display, Serialize)]
+#[display(fmt = "{}", "format_packages(packages)")]
+pub struct OutdatedResult {
+    packages: Vec<OutdatedPackage>,
+}
+
+#[derive(Debug, Display, Error, Serialize)]
+enum Error {
+    #[display(fmt = "Could not find Smaug.toml at {}", "path.display()")]
+    FileNotFound { path: PathBuf },
+    #[display(fmt = "Couldn't load Smaug configuration.")]
+    Config { path: PathBuf },
+    #[display(fmt = "Couldn't load Smaug.lock at {}", "path.display()")]
+    Lockfile { path: PathBuf },
+    #[display(fmt = "Your dependencies aren't installed. Run `smaug install` first.")]
+    NotInstalled,
+    #[display(fmt = "Can't check for newer versions while offline.")]
+    Offline,
+    #[display(fmt = "Could not fetch {} from the registry.", "name")]
+    Registry { name: String },
+    #[display(fmt = "Could not fetch {} from {}.", "name", "repo")]
+    Git { name: String, repo: String },
+}
+
+impl Command for Outdated {
+    fn run(&self, matches: &ArgMatches) -> CommandResult {
+        trace!("Outdated Command");
+
+        let selection = match workspace::select(matches.value_of("path")) {
+            Ok(selection) => selection,
+            Err(path) => return Err(Box::new(Error::FileNotFound { path })),
+        };
+
+        let lock_path = match &selection.workspace {
+            Some(workspace) => workspace.lock_path(),
+            None => selection.members[0].path.join("Smaug.lock"),
+        };
+        let lockfile = match smaug_lib::lockfile::load(&lock_path) {
+            Ok(lockfile) => lockfile,
+            Err(..) => return Err(Box::new(Error::Lockfile { path: lock_path })),
+        };
+
+        for member in selection.members.iter() {
+            let config_path = member.path.join("Smaug.toml");
+
+            let config = match smaug_lib::config::load(&config_path) {
+                Ok(config) => config,
+                Err(..) => return Err(Box::new(Error::Config { path: config_path })),
+            };
+
+            if lockfile.packages.is_empty() && !config.dependencies.is_empty() {
+                return Err(Box::new(Error::NotInstalled));
+            }
+        }
+
+        if smaug_lib::smaug::offline() {
+            return Err(Box::new(Error::Offline));
+        }
+
+        let mut packages = vec![];
+
+        for package in lockfile.packages.iter() {
+            let checked = match (&package.dependency, &package.source) {
+                (DependencyOptions::Registry { version, .. }, LockedSource::Registry { .. }) => {
+                    check_registry(package, version)
+                }
+                (DependencyOptions::Git { rev: None, .. }, LockedSource::Git { .. }) => {
+                    check_git(package)
+                }
+                _ => Ok(None),
+            };
+
+            match checked {
+                Ok(Some(checked)) => packages.push(checked),
+                Ok(None) => {}
+                Err(err) => return Err(Box::new(err)),
+            }
+        }
+
+        if packages.iter().any(|package| package.outdated) {
+            command::set_exit_code(OUTDATED_EXIT_CODE);
+        }
+
+        Ok(Box::new(OutdatedResult { packages }))
+    }
+}
+
+fn check_registry(
+    package: &LockedPackage,
+    requirement: &str,
+) -> Result<Option<OutdatedPackage>, Error> {
+    trace!("Checking {} in the registry", package.name);
+    let registry_error = || Error::Registry {
+        name: package.name.clone(),
+    };
+
+    let name = package.dependency.package().unwrap_or(&package.name);
+    let version_req = VersionReq::parse(requirement).map_err(|_| registry_error())?;
+    let releases = smaug_lib::registry::releases(name).map_err(|_| registry_error())?;
+    let latest = smaug_lib::registry::latest_version(name).map_err(|_| registry_error())?;
+
+    let allowed = releases
+        .iter()
+        .filter_map(|release| Version::parse(release.version.as_str()).ok())
+        .filter(|version| version_req.matches(version))
+        .max()
+        .map(|version| version.to_string())
+        .unwrap_or_else(|| package.version.clone());
+
+    Ok(Some(OutdatedPackage {
+        name: package.name.clone(),
+        source: "registry".to_string(),
+        outdated: package.version != allowed || package.version != latest,
+        current: package.version.clone(),
+        allowed,
+        latest,
+    }))
+}
+
+fn check_git(package: &LockedPackage) -> Result<Option<OutdatedPackage>, Error> {
+    let (repo, branch, tag, current) = match (&package.dependency, &package.source) {
+        (
+            DependencyOptions::Git {
+                repo, branch, tag, ..
+            },
+            LockedSource::Git { rev, .. },
+        ) => (repo, branch, tag, rev),
+        _ => return Ok(None),
+    };
+    trace!("Checking {} for upstream changes", repo);
+
+    let source = GitSource {
+        repo: repo.clone(),
+        branch: branch.clone(),
+        rev: None,
+        tag: tag.clone(),
+        path: None,
+        submodules: false,
+    };
+
+    let upstream = source.remote_revision().map_err(|_| Error::Git {
+        name: package.name.clone(),
+        repo: repo.clone(),
+    })?;
+
+    Ok(Some(OutdatedPackage {
+        name: package.name.clone(),
+        source: "git".to_string(),
+        outdated: *current != upstream,
+        current: current.clone(),
+        allowed: upstream.clone(),
+        latest: upstream,
+    }))
+}
+
+fn format_packages(packages: &[OutdatedPackage]) -> String {
+    if !packages.iter().any(|package| package.outdated) {
+        return "Your dependencies are up to date.".to_string();
+    }
+
+    let short = |package: &OutdatedPackage, value: &str| -> String {
+        if package.source == "git" && value.len() > 7 {
+            value[..7].to_string()
+        } else {
+            value.to_string()
+        }
+    };
+
+    let mut rows = vec![vec![
+        "Package".to_string(),
+        "Current".to_string(),
+        "Allowed".to_string(),
+        "Latest".to_string(),
+    ]];
+
+    for package in packages.iter().filter(|package| package.outdated) {
+        rows.push(vec![
+            package.name.clone(),
+            short(package, &package.current),
+            short(package, &package.allowed),
+            short(package, &package.latest),
+        ]);
+    }
+
+    let widths: Vec<usize> = (0..4)
+        .map(|column| rows.iter().map(|row| row[column].len()).max().unwrap())
+        .collect();
+
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .zip(widths.iter())
+                .map(|(cell, width)| format!("{:width$}", cell, width = width))
+                .collect::<Vec<String>>()
+                .join("  ")
+                .trim_end()
+                .to_string()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
@@ -0,0 +1,153 @@
+use crate::command::Command;
+use crate::command::CommandResult;
+use crate::commands::install::Options;
+use crate::workspace;
+use clap::ArgMatches;
+use derive_more::Display;
+use derive_more::Error;
+use log::*;
+use serde::Serialize;
+use std::path::PathBuf;
+use toml_edit::Document;
+
+pub struct Remove;
+
+#[derive(Debug, Display, Error, Serialize)]
+pub enum Error {
+    #[display(fmt = "Could not find Smaug.toml at {}", "path.display()")]
+    FileNotFound { path: PathBuf },
+    #[display(fmt = "{} is not a dependency of this project.", "name")]
+    NotADependency { name: String },
+    #[display(fmt = "Couldn't load Smaug configuration.")]
+    Config { path: PathBuf },
+    #[display(fmt = "Couldn't load Smaug.lock at {}", "path.display()")]
+    Lockfile { path: PathBuf },
+    #[display(
+        fmt = "Use --path to pick the workspace member to remove {} from.",
+        "name"
+    )]
+    Workspace { name: String },
+}
+
+#[derive(Debug, Display, Serialize)]
+#[display(fmt = "{}", "format_result(self)")]
+pub struct RemoveResult {
+    package: String,
+    still_required: bool,
+    removed_files: Vec<PathBuf>,
+    modified_files: Vec<PathBuf>,
+}
+
+impl Command for Remove {
+    fn run(&self, matches: &ArgMatches) -> CommandResult {
+        trace!("Remove Command");
+
+        let selection = match workspace::select(matches.value_of("path")) {
+            Ok(selection) => selection,
+            Err(path) => return Err(Box::new(Error::FileNotFound { path })),
+        };
+        let package_name = matches.value_of("PACKAGE").expect("No package given");
+
+        if selection.members.len() != 1 {
+            return Err(Box::new(Error::Workspace {
+                name: package_name.to_string(),
+            }));
+        }
+
+        let path = selection.members[0].path.clone();
+        let config_path = path.join("Smaug.toml");
+
+        if !config_path.is_file() {
+            return Err(Box::new(Error::FileNotFound { path: config_path }));
+        }
+
+        let contents =
+            std::fs::read_to_string(config_path.clone()).expect("Could not read Smaug.toml");
+
+        let mut doc = contents.parse::<Document>().expect("invalid doc");
+        let removed = ["dependencies", "dev-dependencies"]
+            .iter()
+            .find_map(|table| {
+                doc[table]
+                    .as_table_mut()
+                    .and_then(|dependencies| dependencies.remove(package_name))
+            });
+
+        if removed.is_none() {
+            return Err(Box::new(Error::NotADependency {
+                name: package_name.to_string(),
+            }));
+        }
+
+        std::fs::write(config_path.clone(), doc.to_string_in_original_order())
+            .expect("Couldn't write config file.");
+
+        let config = match smaug_lib::config::load(&config_path) {
+            Ok(config) => config,
+            Err(..) => return Err(Box::new(Error::Config { path: config_path })),
+        };
+
+        let lock_path = match &selection.workspace {
+            Some(workspace) => workspace.lock_path(),
+            None => path.join("Smaug.lock"),
+        };
+        let lockfile = match smaug_lib::lockfile::load(&lock_path) {
+            Ok(lockfile) => lockfile,
+            Err(..) => return Err(Box::new(Error::Lockfile { path: lock_path })),
+        };
+
+        let options = Options::from_matches(matches)?;
+        let (locked, pruned) = match &selection.workspace {
+            Some(workspace) => {
+                // Every member shares the lockfile, so they're all resolved again.
+                let (merged, result) = crate::commands::install::install_members(
+                    workspace,
+                    &workspace.members,
+                    &lockfile,
+                    &lockfile,
+                    options,
+                )?;
+
+                (merged, result.pruned)
+            }
+            None => {
+                let (registry, _, pruned) =
+                    crate::commands::install::install(&path, &config, &lockfile, &options)?;
+
+                (registry.lockfile, pruned)
+            }
+        };
+
+        let still_required = locked.get(package_name).is_some();
+
+        if still_required {
+            info!(
+                "{} is still required by another package, so it was left installed.",
+                package_name
+            );
+        }
+
+        Ok(Box::new(RemoveResult {
+            package: package_name.to_string(),
+            still_required,
+            removed_files: pruned.files,
+            modified_files: pruned.modified_files,
+        }))
+    }
+}
+
+fn format_result(result: &RemoveResult) -> String {
+    let mut message = format!("Removed {} from your project.", result.package);
+
+    for file in result.modified_files.iter() {
+        message.push_str(
+            format!(
+                "\n{} has local changes and was not removed.",
+                file.display()
+            )
+            .as_str(),
+        );
+    }
+
+    message
+}
@@ -1,4 +1,5 @@
 use crate::command::CommandResult;
+use crate::workspace;
 use crate::{command::Command, game_metadata};
 use clap::ArgMatches;
 use derive_more::Display;
@@ -6,8 +7,6 @@ use derive_more::Error;
 use log::*;
 use serde::Serialize;
 use smaug_lib::dragonruby;
-use std::env;
-use std::path::Path;
 use std::path::PathBuf;
 use std::process;
 use std::fs::{File, create_dir};
@@ -32,6 +31,13 @@ pub enum Error {
     ConfiguredDragonRubyNotFound,
     #[display(fmt = "Couldn't load Smaug configuration.")]
     Config { path: PathBuf },
+    #[display(fmt = "Could not find file at {}", "path.display()")]
+    FileNotFound { path: PathBuf },
+    #[display(
+        fmt = "This is a workspace. Choose a game to run with -p: {}",
+        "members.join(\", \")"
+    )]
+    ChooseMember { members: Vec<String> },
     #[display(
         fmt = "{} crashed look at the logs for more information",
         "project_name"
@@ -40,6 +46,7 @@ pub enum Error {
 }
 
 impl Command for Run {
+    #[allow(clippy::needless_borrows_for_generic_args)]
     fn run(&self, matches: &ArgMatches) -> CommandResult {
         trace!("Run Command");
 
@@ -50,13 +57,19 @@ impl Command for Run {
 
         let httpd = matches.is_present("http");
 
-        let current_directory = env::current_dir().unwrap();
-        let directory: &str = matches
-            .value_of("path")
-            .unwrap_or_else(|| current_directory.to_str().unwrap());
-        debug!("Directory: {}", directory);
-        let path = Path::new(directory);
-        let path = dunce::canonicalize(&path).expect("Could not find path");
+        let selection = match workspace::select(matches.value_of("path")) {
+            Ok(selection) => selection,
+            Err(path) => return Err(Box::new(Error::FileNotFound { path })),
+        };
+
+        let path = match selection.projects().as_slice() {
+            [member] => member.path.clone(),
+            members => {
+                return Err(Box::new(Error::ChooseMember {
+                    members: members.iter().map(|member| member.name.clone()).collect(),
+                }))
+            }
+        };
         debug!("path: {:?}", path);
 
         let config_path = path.join("Smaug.toml");
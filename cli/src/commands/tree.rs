@@ -0,0 +1,283 @@
+use crate::command::Command;
+use crate::command::CommandResult;
+use crate::commands::install;
+use crate::workspace;
+use clap::ArgMatches;
+use derive_more::Display;
+use derive_more::Error;
+use log::*;
+use serde::Serialize;
+use smaug_lib::lockfile::Lockfile;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub struct Tree;
+
+#[derive(Debug, Serialize)]
+pub struct Node {
+    name: String,
+    version: Option<String>,
+    source: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    package: Option<String>,
+    dependencies: Vec<Node>,
+}
+
+/// A game or package whose dependencies are shown, with the names of the
+/// dependencies it declares.
+#[derive(Debug)]
+struct Project {
+    name: String,
+    roots: Vec<String>,
+}
+
+#[derive(Debug, Display, Serialize)]
+#[display(fmt = "{}", "format_tree(self)")]
+pub struct TreeResult {
+    #[serde(skip)]
+    dot: bool,
+    #[serde(flatten)]
+    tree: Node,
+}
+
+#[derive(Debug, Display, Error, Serialize)]
+enum Error {
+    #[display(fmt = "Could not find Smaug.toml at {}", "path.display()")]
+    FileNotFound { path: PathBuf },
+    #[display(fmt = "Couldn't load Smaug configuration.")]
+    Config { path: PathBuf },
+    #[display(fmt = "Couldn't load Smaug.lock at {}", "path.display()")]
+    Lockfile { path: PathBuf },
+    #[display(fmt = "Your dependencies aren't installed. Run `smaug install` first.")]
+    NotInstalled,
+    #[display(fmt = "{} is not a dependency of this project.", "name")]
+    UnknownPackage { name: String },
+}
+
+impl Command for Tree {
+    fn run(&self, matches: &ArgMatches) -> CommandResult {
+        trace!("Tree Command");
+
+        let selection = match workspace::select(matches.value_of("path")) {
+            Ok(selection) => selection,
+            Err(path) => return Err(Box::new(Error::FileNotFound { path })),
+        };
+
+        let lock_path = match &selection.workspace {
+            Some(workspace) => workspace.lock_path(),
+            None => selection.members[0].path.join("Smaug.lock"),
+        };
+        let lockfile = match smaug_lib::lockfile::load(&lock_path) {
+            Ok(lockfile) => lockfile,
+            Err(..) => return Err(Box::new(Error::Lockfile { path: lock_path })),
+        };
+
+        let mut projects = vec![];
+        for member in selection.members.iter() {
+            let config_path = member.path.join("Smaug.toml");
+
+            let config = match smaug_lib::config::load(&config_path) {
+                Ok(config) => config,
+                Err(..) => return Err(Box::new(Error::Config { path: config_path })),
+            };
+
+            if lockfile.packages.is_empty() && !config.dependencies.is_empty() {
+                return Err(Box::new(Error::NotInstalled));
+            }
+
+            let resolver = smaug_lib::resolver::new_from_config(&config);
+            projects.push(Project {
+                name: resolver.name.clone(),
+                roots: install::dependency_names(&resolver),
+            });
+        }
+
+        let tree = match matches.value_of("invert") {
+            None => {
+                let mut nodes: Vec<Node> = projects
+                    .iter()
+                    .map(|project| Node {
+                        name: project.name.clone(),
+                        version: None,
+                        source: None,
+                        package: None,
+                        dependencies: project
+                            .roots
+                            .iter()
+                            .map(|name| dependency_node(name, &lockfile, &mut vec![]))
+                            .collect(),
+                    })
+                    .collect();
+
+                match (nodes.len(), &selection.workspace) {
+                    (1, _) | (_, None) => nodes.remove(0),
+                    (_, Some(workspace)) => Node {
+                        name: workspace
+                            .root
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .into_owned(),
+                        version: None,
+                        source: None,
+                        package: None,
+                        dependencies: nodes,
+                    },
+                }
+            }
+            Some(name) => {
+                if lockfile.get(name).is_none() {
+                    return Err(Box::new(Error::UnknownPackage {
+                        name: name.to_string(),
+                    }));
+                }
+
+                inverted_node(name, &projects, &lockfile, &mut vec![])
+            }
+        };
+
+        Ok(Box::new(TreeResult {
+            dot: matches.value_of("format") == Some("dot"),
+            tree,
+        }))
+    }
+}
+
+fn package_node(name: &str, lockfile: &Lockfile, dependencies: Vec<Node>) -> Node {
+    let package = lockfile.get(name);
+
+    Node {
+        name: name.to_string(),
+        version: package.map(|package| package.version.clone()),
+        source: package.map(|package| package.source.kind().to_string()),
+        package: package.and_then(|package| package.dependency.package().map(str::to_string)),
+        dependencies,
+    }
+}
+
+fn dependency_node(name: &str, lockfile: &Lockfile, stack: &mut Vec<String>) -> Node {
+    let children = match lockfile.get(name) {
+        Some(package) if !stack.iter().any(|parent| parent == name) => {
+            stack.push(name.to_string());
+            let children = package
+                .dependencies
+                .iter()
+                .map(|child| dependency_node(child, lockfile, stack))
+                .collect();
+            stack.pop();
+
+            children
+        }
+        _ => vec![],
+    };
+
+    package_node(name, lockfile, children)
+}
+
+fn inverted_node(
+    name: &str,
+    projects: &[Project],
+    lockfile: &Lockfile,
+    stack: &mut Vec<String>,
+) -> Node {
+    if stack.iter().any(|child| child == name) {
+        return package_node(name, lockfile, vec![]);
+    }
+
+    stack.push(name.to_string());
+
+    let mut dependents: Vec<Node> = lockfile
+        .packages
+        .iter()
+        .filter(|package| package.dependencies.iter().any(|child| child == name))
+        .map(|package| inverted_node(&package.name, projects, lockfile, stack))
+        .collect();
+
+    for project in projects.iter() {
+        if project.roots.iter().any(|root| root == name) {
+            dependents.push(Node {
+                name: project.name.clone(),
+                version: None,
+                source: None,
+                package: None,
+                dependencies: vec![],
+            });
+        }
+    }
+
+    stack.pop();
+
+    package_node(name, lockfile, dependents)
+}
+
+fn format_tree(result: &TreeResult) -> String {
+    if result.dot {
+        format_dot(&result.tree)
+    } else {
+        let mut lines = vec![format_node(&result.tree)];
+        format_children(&result.tree, "", &mut lines);
+
+        lines.join("\n")
+    }
+}
+
+fn format_node(node: &Node) -> String {
+    let source = node.source.as_ref().map(|source| match &node.package {
+        Some(package) => format!("{} package {}", source, package),
+        None => source.clone(),
+    });
+
+    match (&node.version, &source) {
+        (Some(version), Some(source)) => format!("{} {} ({})", node.name, version, source),
+        (None, Some(source)) => format!("{} ({})", node.name, source),
+        _ => node.name.clone(),
+    }
+}
+
+fn format_children(node: &Node, prefix: &str, lines: &mut Vec<String>) {
+    let count = node.dependencies.len();
+
+    for (index, child) in node.dependencies.iter().enumerate() {
+        let last = index + 1 == count;
+        let branch = if last { "└── " } else { "├── " };
+        let indent = if last { "    " } else { "│   " };
+
+        lines.push(format!("{}{}{}", prefix, branch, format_node(child)));
+        format_children(child, format!("{}{}", prefix, indent).as_str(), lines);
+    }
+}
+
+fn format_dot(tree: &Node) -> String {
+    let mut nodes = vec![];
+    let mut edges = vec![];
+    collect_dot(tree, &mut nodes, &mut edges);
+
+    let mut lines = vec!["digraph dependencies {".to_string()];
+    lines.extend(nodes.into_iter().map(|node| format!("    {}", node)));
+    lines.extend(edges.into_iter().map(|edge| format!("    {}", edge)));
+    lines.push("}".to_string());
+
+    lines.join("\n")
+}
+
+fn collect_dot(node: &Node, nodes: &mut Vec<String>, edges: &mut Vec<String>) {
+    let label = format!(
+        "{:?} [label={:?}];",
+        node.name,
+        format_node(node).replacen(' ', "\n", 1)
+    );
+
+    if !nodes.contains(&label) {
+        nodes.push(label);
+    }
+
+    for child in node.dependencies.iter() {
+        let edge = format!("{:?} -> {:?};", node.name, child.name);
+
+        if !edges.contains(&edge) {
+            edges.push(edge);
+        }
+
+        collect_dot(child, nodes, edges);
+    }
+}
@@ -0,0 +1,234 @@
+use crate::command::Command;
+use crate::command::CommandResult;
+use crate::commands::install;
+use crate::commands::install::Options;
+use crate::workspace;
+use clap::ArgMatches;
+use derive_more::Display;
+use derive_more::Error;
+use log::*;
+use serde::Serialize;
+use smaug_lib::lockfile::{LockedPackage, Lockfile};
+use smaug_lib::resolver;
+use std::path::PathBuf;
+
+#[derive(Debug)]
+pub struct Update;
+
+#[derive(Debug, Serialize)]
+pub struct UpdatedPackage {
+    name: String,
+    old_version: Option<String>,
+    new_version: Option<String>,
+    old_revision: Option<String>,
+    new_revision: Option<String>,
+}
+
+#[derive(Debug, Display, Serialize)]
+#[display(fmt = "{}", "format_updates(packages)")]
+pub struct UpdateResult {
+    packages: Vec<UpdatedPackage>,
+}
+
+#[derive(Debug, Display, Error, Serialize)]
+enum Error {
+    #[display(fmt = "Could not find Smaug.toml at {}", "path.display()")]
+    FileNotFound { path: PathBuf },
+    #[display(fmt = "Couldn't load Smaug configuration.")]
+    Config { path: PathBuf },
+    #[display(fmt = "Couldn't load Smaug.lock at {}", "path.display()")]
+    Lockfile { path: PathBuf },
+    #[display(fmt = "{} is not a dependency of this project.", "name")]
+    UnknownPackage { name: String },
+}
+
+impl Command for Update {
+    fn run(&self, matches: &ArgMatches) -> CommandResult {
+        trace!("Update Command");
+
+        let selection = match workspace::select(matches.value_of("path")) {
+            Ok(selection) => selection,
+            Err(path) => return Err(Box::new(Error::FileNotFound { path })),
+        };
+
+        let mut configs = vec![];
+        for member in selection.members.iter() {
+            let config_path = member.path.join("Smaug.toml");
+
+            match smaug_lib::config::load(&config_path) {
+                Ok(config) => configs.push(config),
+                Err(..) => return Err(Box::new(Error::Config { path: config_path })),
+            }
+        }
+        debug!("Smaug config: {:?}", configs);
+
+        let lock_path = match &selection.workspace {
+            Some(workspace) => workspace.lock_path(),
+            None => selection.members[0].path.join("Smaug.lock"),
+        };
+        let lockfile = match smaug_lib::lockfile::load(&lock_path) {
+            Ok(lockfile) => lockfile,
+            Err(..) => return Err(Box::new(Error::Lockfile { path: lock_path })),
+        };
+
+        let names: Vec<&str> = matches.values_of("PACKAGE").unwrap_or_default().collect();
+
+        for name in names.iter() {
+            let declared = configs.iter().any(|config| {
+                config.dependencies.contains_key(*name)
+                    || config.dev_dependencies.contains_key(*name)
+            });
+
+            if lockfile.get(name).is_none() && !declared {
+                return Err(Box::new(Error::UnknownPackage {
+                    name: name.to_string(),
+                }));
+            }
+        }
+
+        let updated_names: Vec<String> = match (&selection.workspace, names.is_empty()) {
+            (_, false) => names.iter().map(|name| name.to_string()).collect(),
+            (Some(..), true) => {
+                let roots: Vec<String> = configs
+                    .iter()
+                    .flat_map(|config| {
+                        install::dependency_names(&resolver::new_from_config(config))
+                    })
+                    .collect();
+
+                install::reachable(&lockfile, &roots)
+            }
+            (None, true) => lockfile
+                .packages
+                .iter()
+                .map(|package| package.name.clone())
+                .collect(),
+        };
+
+        // Smaug.local.toml decides where locally patched packages come from,
+        // so their entries in Smaug.lock stay as they are.
+        let locally_patched = |name: &String| {
+            configs
+                .iter()
+                .any(|config| config.local_patch.contains_key(name))
+                || selection
+                    .workspace
+                    .as_ref()
+                    .is_some_and(|workspace| workspace.local_patch.contains_key(name))
+        };
+
+        let unlocked = Lockfile {
+            packages: lockfile
+                .packages
+                .iter()
+                .filter(|package| {
+                    !updated_names.contains(&package.name) || locally_patched(&package.name)
+                })
+                .cloned()
+                .collect(),
+        };
+
+        let options = Options::from_matches(matches)?;
+        let updated = match &selection.workspace {
+            Some(workspace) => {
+                // Every member shares the lockfile, so they're all resolved again.
+                let (merged, _) = install::install_members(
+                    workspace,
+                    &workspace.members,
+                    &lockfile,
+                    &unlocked,
+                    options,
+                )?;
+
+                merged
+            }
+            None => {
+                let (registry, _, _) =
+                    install::install(&selection.members[0].path, &configs[0], &unlocked, &options)?;
+
+                install::lockable(&configs, &registry.lockfile, &unlocked)
+            }
+        };
+
+        Ok(Box::new(UpdateResult {
+            packages: compare(&lockfile, &updated),
+        }))
+    }
+}
+
+fn compare(old: &Lockfile, new: &Lockfile) -> Vec<UpdatedPackage> {
+    let mut names: Vec<&str> = new
+        .packages
+        .iter()
+        .map(|package| package.name.as_str())
+        .collect();
+
+    for package in old.packages.iter() {
+        if !names.contains(&package.name.as_str()) {
+            names.push(package.name.as_str());
+        }
+    }
+
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let old_package = old.get(name).cloned();
+            let new_package = new.get(name).cloned();
+
+            if old_package == new_package {
+                return None;
+            }
+
+            let revision = |package: &Option<LockedPackage>| {
+                package
+                    .as_ref()
+                    .and_then(|package| package.source.revision().map(str::to_string))
+            };
+
+            let updated = UpdatedPackage {
+                name: name.to_string(),
+                old_revision: revision(&old_package),
+                new_revision: revision(&new_package),
+                old_version: old_package.map(|package| package.version),
+                new_version: new_package.map(|package| package.version),
+            };
+
+            if updated.old_version == updated.new_version
+                && updated.old_revision == updated.new_revision
+            {
+                None
+            } else {
+                Some(updated)
+            }
+        })
+        .collect()
+}
+
+fn format_updates(packages: &[UpdatedPackage]) -> String {
+    if packages.is_empty() {
+        return "Your dependencies are already up to date.".to_string();
+    }
+
+    packages
+        .iter()
+        .map(|package| {
+            format!(
+                "* {} {} -> {}\n",
+                package.name,
+                format_version(&package.old_version, &package.old_revision),
+                format_version(&package.new_version, &package.new_revision)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("")
+}
+
+fn format_version(version: &Option<String>, revision: &Option<String>) -> String {
+    match (version, revision) {
+        (None, _) => "(none)".to_string(),
+        (Some(version), Some(revision)) if revision.len() > 7 => {
+            format!("{} ({})", version, &revision[..7])
+        }
+        (Some(version), _) => version.clone(),
+    }
+}
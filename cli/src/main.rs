@@ -3,6 +3,7 @@ extern crate derive_more;
 mod command;
 mod commands;
 mod game_metadata;
+mod workspace;
 
 use crate::command::Command;
 use crate::commands::bind::Bind;
@@ -12,7 +13,7 @@ use clap::clap_app;
 use commands::install::Install;
 use commands::{
     add::Add, build::Build, config::Config, docs::Docs, dragonruby::DragonRuby, init::Init,
-    new::New, publish::Publish,
+    new::New, outdated::Outdated, publish::Publish, remove::Remove, tree::Tree, update::Update,
 };
 use log::*;
 
@@ -26,6 +27,7 @@ fn main() {
         (@arg verbose: -v... --verbose... +global takes_value(false) "Displays more information")
         (@arg json: --json +global takes_value(false) "Returns JSON")
         (@arg quiet: -q --quiet +global takes_value(false) "Silence all output")
+        (@arg offline: --offline +global takes_value(false) "Only use packages that are already cached. Also set by SMAUG_OFFLINE.")
 
         (@subcommand dragonruby =>
             (about: "Manages your local DragonRuby installation.")
@@ -62,7 +64,7 @@ fn main() {
             (about: "Runs your DragonRuby project.")
             (setting: clap::AppSettings::TrailingVarArg)
             (setting: clap::AppSettings::AllowLeadingHyphen)
-            (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
+            (@arg path: --path -p +takes_value "The path to your project or the name of a workspace member. Defaults to the current directory.")
             (@arg http: --http "Run your HTML5 game")
             (@arg DRAGONRUBY_ARGS: ... "dragonruby command options")
         )
@@ -70,14 +72,14 @@ fn main() {
             (about: "Builds your DragonRuby project.")
             (setting: clap::AppSettings::TrailingVarArg)
             (setting: clap::AppSettings::AllowLeadingHyphen)
-            (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
+            (@arg path: --path -p +takes_value "The path to your project or the name of a workspace member. Defaults to the current directory.")
             (@arg DRAGONRUBY_ARGS: ... "dragonruby command options")
         )
         (@subcommand publish =>
             (about: "Publish your DragonRuby project to Itch.io")
             (setting: clap::AppSettings::TrailingVarArg)
             (setting: clap::AppSettings::AllowLeadingHyphen)
-            (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
+            (@arg path: --path -p +takes_value "The path to your project or the name of a workspace member. Defaults to the current directory.")
             (@arg DRAGONRUBY_ARGS: ... "dragonruby-publish command options")
         )
         (@subcommand bind =>
@@ -92,11 +94,37 @@ fn main() {
         (@subcommand add =>
             (about: "Adds a dependency to the project.")
             (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
-            (@arg PACKAGE: +required "The package to add to your project's dependencies")
+            (@arg PACKAGE: +required "The package to add to your project's dependencies: a registry name, zip file or URL")
         )
         (@subcommand install =>
             (about: "Installs dependencies from Smaug.toml.")
             (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
+            (@arg jobs: -j --jobs +takes_value "The number of packages to fetch at once. Defaults to the number of CPUs.")
+            (@arg force: --force "Reinstalls every dependency, even if it's already installed.")
+            (@arg no_prune: --("no-prune") "Lists packages that are no longer used instead of removing them.")
+            (@arg check: --check "Reports how the project differs from Smaug.toml without changing anything.")
+        )
+        (@subcommand outdated =>
+            (about: "Lists dependencies that have newer versions available.")
+            (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
+        )
+        (@subcommand remove =>
+            (about: "Removes a dependency from the project.")
+            (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
+            (@arg PACKAGE: +required "The package to remove from your project's dependencies")
+            (@arg jobs: -j --jobs +takes_value "The number of packages to fetch at once. Defaults to the number of CPUs.")
+        )
+        (@subcommand tree =>
+            (about: "Shows your project's dependency graph.")
+            (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
+            (@arg format: --format +takes_value possible_values(&["text", "dot"]) "The output format. Defaults to text.")
+            (@arg invert: --invert +takes_value "Shows the packages that depend on the given package.")
+        )
+        (@subcommand update =>
+            (about: "Updates dependencies to the newest versions allowed by Smaug.toml.")
+            (@arg path: --path -p +takes_value "The path to your project. Defaults to the current directory.")
+            (@arg PACKAGE: ... "The packages to update. Defaults to every dependency.")
+            (@arg jobs: -j --jobs +takes_value "The number of packages to fetch at once. Defaults to the number of CPUs.")
         )
         (@subcommand add =>
             (about: "Add a dependency to Smaug.toml")
@@ -121,9 +149,13 @@ fn main() {
         Some("init") => Some(Box::new(Init)),
         Some("install") => Some(Box::new(Install)),
         Some("new") => Some(Box::new(New)),
+        Some("outdated") => Some(Box::new(Outdated)),
         Some("package") => Some(Box::new(Package)),
         Some("publish") => Some(Box::new(Publish)),
+        Some("remove") => Some(Box::new(Remove)),
         Some("run") => Some(Box::new(Run)),
+        Some("tree") => Some(Box::new(Tree)),
+        Some("update") => Some(Box::new(Update)),
         Some("add") => Some(Box::new(Add)),
         Some("bind") => Some(Box::new(Bind)),
         Some("config") => Some(Box::new(Config)),
@@ -134,6 +166,10 @@ fn main() {
     if let Some(cmd) = command {
         start_log(&matches);
 
+        if matches.is_present("offline") {
+            smaug_lib::smaug::set_offline(true);
+        }
+
         let subcommand_matches = matches.subcommand_matches(matches.subcommand_name().unwrap());
 
         let json = matches.is_present("json");
@@ -156,7 +192,11 @@ fn main() {
                 }
             }
         }
-        print_message()
+        print_message();
+
+        if command::exit_code() != 0 {
+            std::process::exit(command::exit_code());
+        }
     }
 }
 
@@ -0,0 +1,102 @@
+use log::*;
+use smaug_lib::workspace::Member;
+use smaug_lib::workspace::Workspace;
+use std::env;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The projects a command acts on: a plain project, every member of a
+/// workspace, or the one member picked with `-p`.
+#[derive(Debug)]
+pub struct Selection {
+    pub workspace: Option<Workspace>,
+    pub members: Vec<Member>,
+}
+
+impl Selection {
+    /// The selected members that are games. Packages in a workspace have
+    /// nothing to run or build.
+    pub fn projects(&self) -> Vec<Member> {
+        match self.workspace {
+            Some(..) => projects(&self.members),
+            None => self.members.clone(),
+        }
+    }
+}
+
+/// The workspace members that are games rather than packages. Installing
+/// into a package would put its dependencies in the package itself.
+pub fn projects(members: &[Member]) -> Vec<Member> {
+    members
+        .iter()
+        .filter(|member| {
+            let is_project = smaug_lib::config::load(&member.path.join("Smaug.toml"))
+                .map(|config| config.project.is_some())
+                .unwrap_or(true);
+
+            if !is_project {
+                debug!("Skipping package {}", member.name);
+            }
+
+            is_project
+        })
+        .cloned()
+        .collect()
+}
+
+/// Picks the projects for `--path`, which also accepts the name of a workspace
+/// member. Returns the path that couldn't be found on failure.
+pub fn select(directory: Option<&str>) -> Result<Selection, PathBuf> {
+    let current_directory = env::current_dir().unwrap();
+    let path = match directory {
+        None => current_directory.clone(),
+        Some(directory) => match dunce::canonicalize(directory) {
+            Ok(path) => path,
+            Err(..) => return select_member(&current_directory, directory),
+        },
+    };
+    debug!("Directory: {}", path.display());
+
+    match smaug_lib::workspace::find(&path) {
+        Ok(Some(workspace)) => {
+            let members = match workspace.member_at(&path) {
+                Some(member) => vec![member.clone()],
+                None => workspace.members.clone(),
+            };
+
+            Ok(Selection {
+                workspace: Some(workspace),
+                members,
+            })
+        }
+        Ok(None) => Ok(Selection {
+            workspace: None,
+            members: vec![Member {
+                name: path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned(),
+                path,
+            }],
+        }),
+        Err(err) => {
+            error!("{}", err);
+            Err(path)
+        }
+    }
+}
+
+fn select_member(current_directory: &Path, name: &str) -> Result<Selection, PathBuf> {
+    let not_found = || Path::new(name).to_path_buf();
+    let workspace = match smaug_lib::workspace::find(current_directory) {
+        Ok(Some(workspace)) => workspace,
+        _ => return Err(not_found()),
+    };
+    let member = workspace.member(name).cloned().ok_or_else(not_found)?;
+
+    Ok(Selection {
+        workspace: Some(workspace),
+        members: vec![member],
+    })
+}
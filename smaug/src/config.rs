@@ -23,6 +23,21 @@ pub struct Config {
     pub itch: Option<Itch>,
     #[serde(default)]
     pub dependencies: LinkedHashMap<String, DependencyOptions>,
+    #[serde(default, rename = "dev-dependencies")]
+    pub dev_dependencies: LinkedHashMap<String, DependencyOptions>,
+    #[serde(default)]
+    pub patch: LinkedHashMap<String, DependencyOptions>,
+    /// Patches from `Smaug.local.toml`, which take precedence over `patch`.
+    #[serde(skip)]
+    pub local_patch: LinkedHashMap<String, DependencyOptions>,
+}
+
+/// The settings a developer can keep out of version control in
+/// `Smaug.local.toml`.
+#[derive(Debug, Deserialize)]
+struct LocalConfig {
+    #[serde(default)]
+    patch: LinkedHashMap<String, DependencyOptions>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -42,6 +57,20 @@ pub struct Package {
     pub installs: LinkedHashMap<RelativePathBuf, RelativePathBuf>,
     #[serde(default)]
     pub requires: Vec<RelativePathBuf>,
+    #[serde(default)]
+    pub features: LinkedHashMap<String, Feature>,
+}
+
+/// Optional parts of a package that a project turns on with
+/// `features = ["name"]` on its dependency.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Feature {
+    #[serde(default)]
+    pub requires: Vec<RelativePathBuf>,
+    #[serde(default)]
+    pub installs: LinkedHashMap<RelativePathBuf, RelativePathBuf>,
+    #[serde(default)]
+    pub dependencies: LinkedHashMap<String, DependencyOptions>,
 }
 
 #[derive(Clone, Debug, Deserialize, Serialize)]
@@ -67,28 +96,276 @@ pub struct Itch {
     pub username: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
 pub enum DependencyOptions {
     Dir {
         dir: PathBuf,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        link: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        features: Vec<String>,
     },
     File {
         file: PathBuf,
+        checksum: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        features: Vec<String>,
     },
     Git {
         branch: Option<String>,
         repo: String,
         rev: Option<String>,
         tag: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        submodules: bool,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        features: Vec<String>,
     },
     Registry {
         version: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        features: Vec<String>,
     },
     Url {
         url: String,
+        checksum: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        version: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        package: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        features: Vec<String>,
     },
 }
 
+impl Config {
+    /// Every dependency source override, with `Smaug.local.toml` winning over
+    /// `Smaug.toml`.
+    pub fn patches(&self) -> LinkedHashMap<String, DependencyOptions> {
+        let mut patches = self.patch.clone();
+
+        for (name, options) in self.local_patch.iter() {
+            patches.insert(name.clone(), options.clone());
+        }
+
+        patches
+    }
+}
+
+impl DependencyOptions {
+    /// The package features this dependency turns on.
+    pub fn features(&self) -> &[String] {
+        match self {
+            DependencyOptions::Dir { features, .. }
+            | DependencyOptions::File { features, .. }
+            | DependencyOptions::Git { features, .. }
+            | DependencyOptions::Registry { features, .. }
+            | DependencyOptions::Url { features, .. } => features,
+        }
+    }
+
+    pub fn is_registry(&self) -> bool {
+        matches!(self, DependencyOptions::Registry { .. })
+    }
+
+    /// The version requirement the installed package has to meet, if any.
+    pub fn version(&self) -> Option<&str> {
+        match self {
+            DependencyOptions::Registry { version, .. } => Some(version),
+            DependencyOptions::Dir { version, .. }
+            | DependencyOptions::File { version, .. }
+            | DependencyOptions::Git { version, .. }
+            | DependencyOptions::Url { version, .. } => version.as_deref(),
+        }
+    }
+
+    /// The name of the package upstream, when it's installed under another
+    /// name.
+    pub fn package(&self) -> Option<&str> {
+        match self {
+            DependencyOptions::Dir { package, .. }
+            | DependencyOptions::File { package, .. }
+            | DependencyOptions::Git { package, .. }
+            | DependencyOptions::Registry { package, .. }
+            | DependencyOptions::Url { package, .. } => package.as_deref(),
+        }
+    }
+
+    /// Moves a relative `dir` or `file` under `base`, so a path declared by a
+    /// package is read from that package rather than from the project.
+    pub fn rebased(&self, base: &Path) -> DependencyOptions {
+        let mut rebased = self.clone();
+
+        match &mut rebased {
+            DependencyOptions::Dir { dir: path, .. }
+            | DependencyOptions::File { file: path, .. }
+                if path.is_relative() =>
+            {
+                *path = base.join(&path);
+            }
+            _ => {}
+        }
+
+        rebased
+    }
+
+    /// Whether both dependencies install the same package from the same place,
+    /// whatever features or version requirements they ask for. Relative paths
+    /// are compared from `base`. Registry dependencies are left to the solver.
+    pub fn same_source(&self, other: &DependencyOptions, base: &Path) -> bool {
+        if self.is_registry() && other.is_registry() {
+            return self.package() == other.package();
+        }
+
+        self.source(base) == other.source(base)
+    }
+
+    fn source(&self, base: &Path) -> DependencyOptions {
+        let mut source = self.rebased(base);
+
+        match &mut source {
+            DependencyOptions::Dir {
+                dir: path,
+                link,
+                version,
+                features,
+                ..
+            } => {
+                *path = dunce::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                *link = false;
+                *version = None;
+                features.clear();
+            }
+            DependencyOptions::File {
+                file: path,
+                version,
+                features,
+                ..
+            } => {
+                *path = dunce::canonicalize(&path).unwrap_or_else(|_| path.clone());
+                *version = None;
+                features.clear();
+            }
+            DependencyOptions::Git {
+                version, features, ..
+            }
+            | DependencyOptions::Url {
+                version, features, ..
+            } => {
+                *version = None;
+                features.clear();
+            }
+            DependencyOptions::Registry { features, .. } => features.clear(),
+        }
+
+        source
+    }
+
+    /// Swaps in the source from a patch, keeping the features, package name and
+    /// version requirement this dependency asked for unless the patch lists its
+    /// own.
+    pub fn patched(&self, patch: &DependencyOptions) -> DependencyOptions {
+        let mut patched = patch.clone();
+        let keep_features = patched.features().is_empty();
+
+        match &mut patched {
+            DependencyOptions::Dir {
+                package, features, ..
+            }
+            | DependencyOptions::File {
+                package, features, ..
+            }
+            | DependencyOptions::Git {
+                package, features, ..
+            }
+            | DependencyOptions::Registry {
+                package, features, ..
+            }
+            | DependencyOptions::Url {
+                package, features, ..
+            } => {
+                if keep_features {
+                    *features = self.features().to_vec();
+                }
+
+                if package.is_none() {
+                    *package = self.package().map(str::to_string);
+                }
+            }
+        }
+
+        match &mut patched {
+            DependencyOptions::Dir { version, .. }
+            | DependencyOptions::File { version, .. }
+            | DependencyOptions::Git { version, .. }
+            | DependencyOptions::Url { version, .. } => {
+                if version.is_none() {
+                    *version = self.version().map(str::to_string);
+                }
+            }
+            DependencyOptions::Registry { .. } => {}
+        }
+
+        patched
+    }
+}
+
+impl fmt::Display for DependencyOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DependencyOptions::Dir { dir, link, .. } => {
+                write!(f, "{}", dir.display())?;
+
+                if *link {
+                    write!(f, " (linked)")?;
+                }
+
+                Ok(())
+            }
+            DependencyOptions::File { file, .. } => write!(f, "{}", file.display()),
+            DependencyOptions::Git {
+                repo,
+                branch,
+                rev,
+                tag,
+                path,
+                ..
+            } => {
+                match (branch, tag, rev) {
+                    (_, _, Some(rev)) => write!(f, "{} (rev {})", repo, rev),
+                    (_, Some(tag), None) => write!(f, "{} (tag {})", repo, tag),
+                    (Some(branch), None, None) => write!(f, "{} (branch {})", repo, branch),
+                    (None, None, None) => write!(f, "{}", repo),
+                }?;
+
+                match path {
+                    Some(path) => write!(f, " in {}", path.display()),
+                    None => Ok(()),
+                }
+            }
+            DependencyOptions::Registry { version, .. } => write!(f, "registry {}", version),
+            DependencyOptions::Url { url, .. } => write!(f, "{}", url),
+        }
+    }
+}
+
 #[derive(Debug, Display, Error)]
 pub enum Error {
     #[display(fmt = "Could not find Smaug.toml at {}", "path.display()")]
@@ -117,9 +394,34 @@ pub fn load<P: AsRef<Path>>(path: &P) -> Result<Config, Error> {
         return Err(Error::FileNotFound { path });
     }
 
-    std::env::set_current_dir(&path.parent().unwrap()).unwrap();
+    std::env::set_current_dir(path.parent().unwrap()).unwrap();
     let contents = std::fs::read_to_string(path.clone()).expect("Could not read Smaug.toml");
-    from_str(&contents, &path)
+    let mut config = from_str(&contents, &path)?;
+    config.local_patch = local_patch(&path)?;
+
+    Ok(config)
+}
+
+/// Where the untracked `Smaug.local.toml` next to a `Smaug.toml` lives.
+pub fn local_path(path: &Path) -> PathBuf {
+    path.with_file_name("Smaug.local.toml")
+}
+
+/// Loads the patches from the `Smaug.local.toml` next to a `Smaug.toml`.
+pub fn local_patch(path: &Path) -> Result<LinkedHashMap<String, DependencyOptions>, Error> {
+    let path = local_path(path);
+
+    if !path.is_file() {
+        return Ok(LinkedHashMap::new());
+    }
+
+    let contents = std::fs::read_to_string(&path).expect("Could not read Smaug.local.toml");
+    let local: LocalConfig = toml::from_str(&contents).map_err(|err| Error::ParseError {
+        path: path.to_path_buf(),
+        parent: err,
+    })?;
+
+    Ok(local.patch)
 }
 
 pub fn from_str<S: AsRef<str>>(contents: &S, path: &Path) -> Result<Config, Error> {
@@ -164,6 +466,8 @@ impl<'de> Deserialize<'de> for DependencyOptions {
                 if VersionReq::parse(value).is_ok() {
                     Ok(DependencyOptions::Registry {
                         version: value.to_string(),
+                        package: None,
+                        features: vec![],
                     })
                 } else if let Some("git") = path.extension().and_then(|str| str.to_str()) {
                     Ok(DependencyOptions::Git {
@@ -171,18 +475,35 @@ impl<'de> Deserialize<'de> for DependencyOptions {
                         branch: None,
                         rev: None,
                         tag: None,
+                        path: None,
+                        submodules: false,
+                        version: None,
+                        package: None,
+                        features: vec![],
                     })
                 } else if path.is_dir() {
-                    let canonical =
-                        dunce::canonicalize(path.clone()).expect("Could not find path.");
-                    Ok(DependencyOptions::Dir { dir: canonical })
+                    Ok(DependencyOptions::Dir {
+                        dir: path,
+                        link: false,
+                        version: None,
+                        package: None,
+                        features: vec![],
+                    })
                 } else if path.is_file() {
                     Ok(DependencyOptions::File {
                         file: path.to_path_buf(),
+                        checksum: None,
+                        version: None,
+                        package: None,
+                        features: vec![],
                     })
                 } else if let Ok(_url) = url::Url::parse(value) {
                     Ok(DependencyOptions::Url {
                         url: value.to_string(),
+                        checksum: None,
+                        version: None,
+                        package: None,
+                        features: vec![],
                     })
                 } else {
                     Err(de::Error::invalid_value(
@@ -204,6 +525,12 @@ impl<'de> Deserialize<'de> for DependencyOptions {
                 let mut file: Option<String> = None;
                 let mut version: Option<String> = None;
                 let mut url: Option<String> = None;
+                let mut checksum: Option<String> = None;
+                let mut path: Option<String> = None;
+                let mut submodules = false;
+                let mut link = false;
+                let mut package: Option<String> = None;
+                let mut features: Vec<String> = vec![];
 
                 while let Some(key) = map.next_key()? {
                     match key {
@@ -215,6 +542,12 @@ impl<'de> Deserialize<'de> for DependencyOptions {
                         "file" => file = Some(map.next_value()?),
                         "version" => version = Some(map.next_value()?),
                         "url" => url = Some(map.next_value()?),
+                        "checksum" => checksum = Some(map.next_value()?),
+                        "path" => path = Some(map.next_value()?),
+                        "submodules" => submodules = map.next_value()?,
+                        "link" => link = map.next_value()?,
+                        "package" => package = Some(map.next_value()?),
+                        "features" => features = map.next_value()?,
                         _ => unreachable!(),
                     }
                 }
@@ -225,19 +558,42 @@ impl<'de> Deserialize<'de> for DependencyOptions {
                         branch,
                         tag,
                         rev,
+                        path: path.map(PathBuf::from),
+                        submodules,
+                        version,
+                        package,
+                        features,
                     })
                 } else if let Some(dir) = dir {
                     Ok(DependencyOptions::Dir {
                         dir: Path::new(&dir).to_path_buf(),
+                        link,
+                        version,
+                        package,
+                        features,
                     })
                 } else if let Some(file) = file {
                     Ok(DependencyOptions::File {
                         file: Path::new(&file).to_path_buf(),
+                        checksum,
+                        version,
+                        package,
+                        features,
                     })
-                } else if let Some(version) = version {
-                    Ok(DependencyOptions::Registry { version })
                 } else if let Some(url) = url {
-                    Ok(DependencyOptions::Url { url })
+                    Ok(DependencyOptions::Url {
+                        url,
+                        checksum,
+                        version,
+                        package,
+                        features,
+                    })
+                } else if let Some(version) = version {
+                    Ok(DependencyOptions::Registry {
+                        version,
+                        package,
+                        features,
+                    })
                 } else {
                     Err(de::Error::invalid_value(
                         de::Unexpected::Map,
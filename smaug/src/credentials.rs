@@ -0,0 +1,331 @@
+use git2::Cred;
+use git2::CredentialType;
+use git2::RemoteCallbacks;
+use linked_hash_map::LinkedHashMap;
+use log::*;
+use reqwest::blocking::RequestBuilder;
+use serde::Deserialize;
+use std::env;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Secrets for private packages, read from `credentials.toml` in the user's
+/// Smaug config directory so they never end up in a project.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Credentials {
+    /// Tokens for zip downloads and HTTPS git repositories, by host name.
+    #[serde(default)]
+    pub hosts: LinkedHashMap<String, HostCredentials>,
+    /// API tokens, by registry URL.
+    #[serde(default)]
+    pub registries: LinkedHashMap<String, RegistryCredentials>,
+    #[serde(default)]
+    pub ssh: SshCredentials,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct HostCredentials {
+    pub token: String,
+    /// The user name git sends along with the token.
+    pub username: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct RegistryCredentials {
+    pub token: String,
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct SshCredentials {
+    /// Private keys to try after ssh-agent, before the default ones in
+    /// `~/.ssh`.
+    #[serde(default)]
+    pub keys: Vec<PathBuf>,
+}
+
+pub fn path() -> PathBuf {
+    crate::smaug::config_dir().join("credentials.toml")
+}
+
+/// Loads the user's credentials. Having none is fine.
+pub fn load() -> io::Result<Credentials> {
+    let path = path();
+
+    if !path.is_file() {
+        return Ok(Credentials::default());
+    }
+
+    trace!("Reading credentials from {}", path.display());
+    let contents = std::fs::read_to_string(&path)?;
+
+    toml::from_str(&contents).map_err(|err| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Could not parse {}: {}", path.display(), err),
+        )
+    })
+}
+
+impl Credentials {
+    /// Adds the host's bearer token to a download, falling back to the
+    /// login in `.netrc`.
+    pub fn authorize(&self, request: RequestBuilder, url: &str) -> RequestBuilder {
+        let host = match host(url) {
+            Some(host) => host,
+            None => return request,
+        };
+
+        if let Some(credentials) = self.hosts.get(&host) {
+            debug!("Using the token for {}", host);
+            return request.bearer_auth(&credentials.token);
+        }
+
+        match netrc(&host) {
+            Some((login, password)) => {
+                debug!("Using the .netrc login for {}", host);
+                request.basic_auth(login, Some(password))
+            }
+            None => request,
+        }
+    }
+
+    /// Adds the registry's API token, from `SMAUG_REGISTRY_TOKEN` or the
+    /// credentials file.
+    pub fn authorize_registry(&self, request: RequestBuilder, registry: &str) -> RequestBuilder {
+        let token = env::var("SMAUG_REGISTRY_TOKEN")
+            .ok()
+            .filter(|token| !token.is_empty())
+            .or_else(|| {
+                self.registries
+                    .get(registry.trim_end_matches('/'))
+                    .map(|credentials| credentials.token.clone())
+            });
+
+        match token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Answers git's requests for credentials. Each method is tried once, in
+    /// order: ssh-agent, key files, then the host's token, git's credential
+    /// helpers and finally the system's default credentials.
+    pub fn remote_callbacks(&self, url: &str) -> RemoteCallbacks<'static> {
+        let token = host(url).and_then(|host| self.hosts.get(&host).cloned());
+        let keys = self.ssh_keys();
+        let url = url.to_string();
+
+        let mut tried_agent = false;
+        let mut tried_keys = 0;
+        let mut tried_token = false;
+        let mut tried_helper = false;
+        let mut tried_default = false;
+
+        let mut callbacks = RemoteCallbacks::new();
+        callbacks.credentials(move |_, username, allowed| {
+            let username = username.unwrap_or("git");
+
+            if allowed.contains(CredentialType::USERNAME) {
+                return Cred::username(username);
+            }
+
+            if allowed.contains(CredentialType::SSH_KEY) {
+                if !tried_agent {
+                    tried_agent = true;
+                    trace!("Trying ssh-agent for {}", url);
+                    if let Ok(cred) = Cred::ssh_key_from_agent(username) {
+                        return Ok(cred);
+                    }
+                }
+
+                while tried_keys < keys.len() {
+                    let key = &keys[tried_keys];
+                    tried_keys += 1;
+                    trace!("Trying {} for {}", key.display(), url);
+                    if let Ok(cred) = Cred::ssh_key(username, None, key, None) {
+                        return Ok(cred);
+                    }
+                }
+            }
+
+            if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+                if !tried_token {
+                    tried_token = true;
+                    if let Some(token) = &token {
+                        trace!("Using the token for {}", url);
+                        let username = token.username.as_deref().unwrap_or(username);
+                        return Cred::userpass_plaintext(username, &token.token);
+                    }
+                }
+
+                if !tried_helper {
+                    tried_helper = true;
+                    trace!("Asking git's credential helpers for {}", url);
+                    if let Ok(config) = git2::Config::open_default() {
+                        if let Ok(cred) = Cred::credential_helper(&config, &url, None) {
+                            return Ok(cred);
+                        }
+                    }
+                }
+            }
+
+            if allowed.contains(CredentialType::DEFAULT) && !tried_default {
+                tried_default = true;
+                return Cred::default();
+            }
+
+            Err(git2::Error::from_str(&format!(
+                "no credentials for {} worked. Add them to {}",
+                url,
+                path().display()
+            )))
+        });
+
+        callbacks
+    }
+
+    fn ssh_keys(&self) -> Vec<PathBuf> {
+        let mut keys: Vec<PathBuf> = self
+            .ssh
+            .keys
+            .iter()
+            .filter_map(|key| {
+                let expanded = shellexpand::full(&key.to_string_lossy()).ok()?.into_owned();
+
+                Some(PathBuf::from(expanded))
+            })
+            .collect();
+
+        if let Some(home) = directories::BaseDirs::new().map(|dirs| dirs.home_dir().join(".ssh")) {
+            for name in ["id_ed25519", "id_ecdsa", "id_rsa"] {
+                let key = home.join(name);
+
+                if key.is_file() && !keys.contains(&key) {
+                    keys.push(key);
+                }
+            }
+        }
+
+        keys
+    }
+}
+
+fn host(url: &str) -> Option<String> {
+    url::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+}
+
+/// Finds the login for `host` in `.netrc`, or its `default` entry.
+fn netrc(host: &str) -> Option<(String, String)> {
+    let path = match env::var_os("NETRC") {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let home = directories::BaseDirs::new()?.home_dir().to_path_buf();
+            let name = if cfg!(windows) { "_netrc" } else { ".netrc" };
+
+            home.join(name)
+        }
+    };
+
+    parse_netrc(&path, host)
+}
+
+fn parse_netrc(path: &Path, host: &str) -> Option<(String, String)> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut tokens = contents.split_whitespace();
+
+    let mut matched: Option<(Option<String>, Option<String>)> = None;
+    let mut default: Option<(Option<String>, Option<String>)> = None;
+    let mut current: Option<&mut (Option<String>, Option<String>)> = None;
+
+    while let Some(token) = tokens.next() {
+        match token {
+            "machine" => {
+                let machine = tokens.next()?;
+                current = if machine == host && matched.is_none() {
+                    Some(matched.insert((None, None)))
+                } else {
+                    None
+                };
+            }
+            "default" => {
+                current = if default.is_none() {
+                    Some(default.insert((None, None)))
+                } else {
+                    None
+                };
+            }
+            "login" => {
+                let login = tokens.next()?.to_string();
+                if let Some(entry) = current.as_mut() {
+                    entry.0 = Some(login);
+                }
+            }
+            "password" => {
+                let password = tokens.next()?.to_string();
+                if let Some(entry) = current.as_mut() {
+                    entry.1 = Some(password);
+                }
+            }
+            "account" | "macdef" => {
+                tokens.next();
+            }
+            _ => {}
+        }
+    }
+
+    match matched.or(default)? {
+        (Some(login), Some(password)) => Some((login, password)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn netrc(contents: &str, host: &str) -> Option<(String, String)> {
+        let path = env::temp_dir().join(format!("smaug-netrc-{}-{}", std::process::id(), host));
+        std::fs::write(&path, contents).unwrap();
+        let credentials = parse_netrc(&path, host);
+        std::fs::remove_file(&path).unwrap();
+
+        credentials
+    }
+
+    #[test]
+    fn reads_the_matching_machine() {
+        let contents = "default login anon password guest\n\
+                        machine example.com login other password nope\n\
+                        machine git.example.com\n  login me\n  account work\n  password secret\n";
+
+        assert_eq!(
+            netrc(contents, "git.example.com"),
+            Some(("me".to_string(), "secret".to_string()))
+        );
+    }
+
+    #[test]
+    fn falls_back_to_default() {
+        let contents = "machine example.com login other password nope\n\
+                        default login anon password guest\n";
+
+        assert_eq!(
+            netrc(contents, "fallback.example.com"),
+            Some(("anon".to_string(), "guest".to_string()))
+        );
+    }
+
+    #[test]
+    fn needs_a_login_and_password() {
+        assert_eq!(
+            netrc(
+                "machine partial.example.com login me\n",
+                "partial.example.com"
+            ),
+            None
+        );
+    }
+}
@@ -4,4 +4,14 @@ use serde::Serialize;
 pub struct Dependency {
     pub name: String,
     pub version: String,
+    /// The name of the package upstream, when it differs from `name`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub package: Option<String>,
+}
+
+impl Dependency {
+    /// The name to look the package up by in the registry.
+    pub fn package(&self) -> &str {
+        self.package.as_deref().unwrap_or(&self.name)
+    }
 }
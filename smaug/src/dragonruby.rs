@@ -126,6 +126,7 @@ pub fn configured_version(config: &Config) -> Option<DragonRuby> {
     matched.map(|dragonruby| dragonruby.to_owned())
 }
 
+#[allow(clippy::result_filter_map)]
 pub fn list_installed() -> io::Result<Vec<DragonRuby>> {
     let location = smaug::data_dir().join("dragonruby");
     fs::create_dir_all(location.as_path())?;
@@ -180,10 +181,14 @@ pub fn dragonruby_publish_name() -> String {
 }
 
 fn parse_dragonruby_zip(path: &Path) -> DragonRubyResult {
-    let cache = smaug::cache_dir();
+    let digest = crate::util::digest::file(path).expect("Could not read zip");
+    let cache = smaug::cache_dir().join("dragonruby").join(digest);
     trace!("Unzipping DragonRuby from {}", path.display());
-    rm_rf::ensure_removed(cache.clone()).expect("Couldn't clear cache");
-    zip_extensions::zip_extract(&path.to_path_buf(), &cache).expect("Could not extract zip");
+    smaug::fill_cache(&cache, |partial| {
+        zip_extensions::zip_extract(&path.to_path_buf(), &partial.to_path_buf())
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))
+    })
+    .expect("Could not extract zip");
     trace!("Unzipped DragonRuby to {}", cache.display());
 
     parse_dragonruby_dir(&cache)
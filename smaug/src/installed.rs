@@ -0,0 +1,108 @@
+use crate::config::DependencyOptions;
+use crate::lockfile::LockedPackage;
+use crate::lockfile::LockedSource;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// The record kept in `smaug/.installed` of what was copied into
+/// `smaug/<name>`, so an install can tell when it no longer matches.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct InstalledPackage {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub requires: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dev: bool,
+    /// The digest of a copied directory package, to notice when it changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub digest: Option<String>,
+    pub dependency: DependencyOptions,
+    pub source: LockedSource,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<InstalledFile>,
+}
+
+/// A file copied into the project by `package.installs`, relative to the
+/// project, with the digest of the contents Smaug wrote.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct InstalledFile {
+    pub path: PathBuf,
+    pub digest: String,
+}
+
+pub fn path(destination: &Path, name: &str) -> PathBuf {
+    destination
+        .join(".installed")
+        .join(format!("{}.toml", name))
+}
+
+pub fn load(destination: &Path, name: &str) -> Option<InstalledPackage> {
+    let contents = std::fs::read_to_string(path(destination, name)).ok()?;
+
+    toml::from_str(&contents).ok()
+}
+
+/// Loads every record in `smaug/.installed`, sorted by package name.
+pub fn all(destination: &Path) -> Vec<InstalledPackage> {
+    let entries = match std::fs::read_dir(destination.join(".installed")) {
+        Ok(entries) => entries,
+        Err(..) => return vec![],
+    };
+
+    let mut records: Vec<InstalledPackage> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let path = entry.path();
+            let name = path.file_stem()?.to_str()?.to_string();
+
+            load(destination, &name)
+        })
+        .collect();
+    records.sort_by(|a, b| a.name.cmp(&b.name));
+
+    records
+}
+
+pub fn remove(destination: &Path, name: &str) -> io::Result<()> {
+    let path = path(destination, name);
+
+    if path.is_file() {
+        std::fs::remove_file(path)?;
+    }
+
+    Ok(())
+}
+
+impl InstalledPackage {
+    pub fn write(&self, destination: &Path) -> io::Result<()> {
+        let path = path(destination, &self.name);
+        let contents = toml::to_string(self)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+        std::fs::create_dir_all(path.parent().unwrap())?;
+        std::fs::write(path, contents)
+    }
+
+    pub fn matches(&self, package: &LockedPackage) -> bool {
+        self.dependency == package.dependency && self.source == package.source
+    }
+}
+
+impl From<&LockedPackage> for InstalledPackage {
+    fn from(package: &LockedPackage) -> Self {
+        InstalledPackage {
+            name: package.name.clone(),
+            version: package.version.clone(),
+            requires: vec![],
+            dev: package.dev,
+            digest: None,
+            dependency: package.dependency.clone(),
+            source: package.source.clone(),
+            files: vec![],
+        }
+    }
+}
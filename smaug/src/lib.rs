@@ -5,12 +5,18 @@ extern crate semver;
 extern crate shellexpand;
 
 pub mod config;
+pub mod credentials;
 pub mod dependency;
 pub mod dragonruby;
+pub mod installed;
 pub mod itch;
+pub mod lockfile;
 pub mod project;
+pub mod registry;
 pub mod resolver;
 pub mod smaug;
+pub mod solver;
 pub mod source;
 pub mod sources;
 pub mod util;
+pub mod workspace;
@@ -0,0 +1,203 @@
+use crate::config::DependencyOptions;
+use crate::source::Source;
+use crate::sources::dir_source::DirSource;
+use crate::sources::file_source::FileSource;
+use crate::sources::git_source::GitSource;
+use crate::sources::url_source::UrlSource;
+use derive_more::Display;
+use derive_more::Error;
+use serde::Deserialize;
+use serde::Serialize;
+use std::path::Path;
+use std::path::PathBuf;
+
+static HEADER: &str = "# This file is automatically @generated by Smaug.
+# It is not intended for manual editing.
+
+";
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Lockfile {
+    #[serde(default, rename = "package")]
+    pub packages: Vec<LockedPackage>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct LockedPackage {
+    pub name: String,
+    pub version: String,
+    #[serde(default)]
+    pub dependencies: Vec<String>,
+    /// Only needed by the project's dev-dependencies.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dev: bool,
+    pub dependency: DependencyOptions,
+    pub source: LockedSource,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum LockedSource {
+    Dir {
+        dir: PathBuf,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        link: bool,
+    },
+    File {
+        file: PathBuf,
+        digest: String,
+    },
+    Git {
+        repo: String,
+        rev: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        path: Option<PathBuf>,
+        #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+        submodules: bool,
+    },
+    Registry {
+        version: String,
+        repo: String,
+        tag: String,
+        rev: String,
+    },
+    Url {
+        url: String,
+        digest: String,
+    },
+}
+
+#[derive(Debug, Display, Error)]
+pub enum Error {
+    #[display(
+        fmt = "Could not parse Smaug.lock at {}: {}",
+        "path.display()",
+        "parent"
+    )]
+    ParseError {
+        path: PathBuf,
+        parent: toml::de::Error,
+    },
+    #[display(fmt = "Could not write Smaug.lock at {}", "path.display()")]
+    WriteError { path: PathBuf },
+}
+
+pub fn load<P: AsRef<Path>>(path: &P) -> Result<Lockfile, Error> {
+    let path = path.as_ref();
+
+    if !path.is_file() {
+        return Ok(Lockfile::default());
+    }
+
+    let contents = std::fs::read_to_string(path).expect("Could not read Smaug.lock");
+
+    match toml::from_str(contents.as_str()) {
+        Ok(lockfile) => Ok(lockfile),
+        Err(err) => Err(Error::ParseError {
+            path: path.to_path_buf(),
+            parent: err,
+        }),
+    }
+}
+
+impl Lockfile {
+    pub fn write<P: AsRef<Path>>(&self, path: &P) -> Result<(), Error> {
+        let path = path.as_ref();
+        let error = || Error::WriteError {
+            path: path.to_path_buf(),
+        };
+
+        let contents = toml::to_string(self).map_err(|_| error())?;
+        std::fs::write(path, format!("{}{}", HEADER, contents)).map_err(|_| error())
+    }
+
+    pub fn get(&self, name: &str) -> Option<&LockedPackage> {
+        self.packages.iter().find(|package| package.name == name)
+    }
+}
+
+impl LockedPackage {
+    /// Moves relative directory and file paths under `base`, resolving `..`
+    /// so the same directory is always written the same way.
+    pub fn relocated(&self, base: &Path) -> LockedPackage {
+        let relocate = |path: &mut PathBuf| {
+            if path.is_relative() {
+                *path = crate::util::dir::normalize(&base.join(&path));
+            }
+        };
+        let mut package = self.clone();
+
+        match &mut package.dependency {
+            DependencyOptions::Dir { dir: path, .. }
+            | DependencyOptions::File { file: path, .. } => relocate(path),
+            _ => {}
+        }
+
+        match &mut package.source {
+            LockedSource::Dir { dir: path, .. } | LockedSource::File { file: path, .. } => {
+                relocate(path)
+            }
+            _ => {}
+        }
+
+        package
+    }
+}
+
+impl LockedSource {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LockedSource::Dir { .. } => "dir",
+            LockedSource::File { .. } => "file",
+            LockedSource::Git { .. } => "git",
+            LockedSource::Registry { .. } => "registry",
+            LockedSource::Url { .. } => "url",
+        }
+    }
+
+    pub fn revision(&self) -> Option<&str> {
+        match self {
+            LockedSource::Dir { .. } => None,
+            LockedSource::File { digest, .. } | LockedSource::Url { digest, .. } => Some(digest),
+            LockedSource::Git { rev, .. } | LockedSource::Registry { rev, .. } => Some(rev),
+        }
+    }
+
+    pub fn to_source(&self) -> Box<dyn Source> {
+        match self {
+            LockedSource::Dir { dir, link } => Box::new(DirSource {
+                path: dir.clone(),
+                link: *link,
+            }),
+            LockedSource::File { file, digest } => Box::new(FileSource {
+                path: file.clone(),
+                checksum: Some(crate::util::digest::checksum(digest)),
+            }),
+            LockedSource::Git {
+                repo,
+                rev,
+                path,
+                submodules,
+            } => Box::new(GitSource {
+                repo: repo.clone(),
+                branch: None,
+                rev: Some(rev.clone()),
+                tag: None,
+                path: path.clone(),
+                submodules: *submodules,
+            }),
+            LockedSource::Registry { repo, rev, .. } => Box::new(GitSource {
+                repo: repo.clone(),
+                branch: None,
+                rev: Some(rev.clone()),
+                tag: None,
+                path: None,
+                submodules: false,
+            }),
+            LockedSource::Url { url, digest } => Box::new(UrlSource {
+                url: url.clone(),
+                checksum: Some(crate::util::digest::checksum(digest)),
+            }),
+        }
+    }
+}
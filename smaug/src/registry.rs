@@ -0,0 +1,183 @@
+use crate::solver::Candidate;
+use crate::solver::Provider;
+use linked_hash_map::LinkedHashMap;
+use log::*;
+use semver::Version;
+use semver::VersionReq;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::io;
+use std::path::PathBuf;
+
+static REGISTRY_URL: &str = "https://api.smaug.dev";
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Repository {
+    pub url: String,
+    pub tag: String,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct Release {
+    pub version: String,
+    pub repository: Repository,
+    #[serde(default)]
+    pub dependencies: LinkedHashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseResponse {
+    version: Release,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleasesResponse {
+    versions: Vec<Release>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LatestVersionResponse {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PackageResponse {
+    version: LatestVersionResponse,
+}
+
+pub fn latest_version(name: &str) -> io::Result<String> {
+    let url = format!("{}/packages/{}", REGISTRY_URL, name);
+    let response: PackageResponse = fetch(url.as_str(), || {
+        format!("Couldn't fetch {} from repository", name)
+    })?;
+
+    Ok(response.version.version)
+}
+
+pub fn release(name: &str, version: &str) -> io::Result<Release> {
+    let url = format!("{}/packages/{}/versions/{}", REGISTRY_URL, name, version);
+    let response: ReleaseResponse = fetch(url.as_str(), || {
+        format!(
+            "Couldn't fetch {} version {} from repository",
+            name, version
+        )
+    })?;
+
+    Ok(response.version)
+}
+
+pub fn releases(name: &str) -> io::Result<Vec<Release>> {
+    let url = format!("{}/packages/{}/versions", REGISTRY_URL, name);
+    let response: ReleasesResponse = fetch(url.as_str(), || {
+        format!("Couldn't fetch the versions of {} from repository", name)
+    })?;
+
+    Ok(response.versions)
+}
+
+fn fetch<T, F>(url: &str, not_found: F) -> io::Result<T>
+where
+    T: DeserializeOwned,
+    F: Fn() -> String,
+{
+    let cached = cache_path(url);
+
+    if crate::smaug::offline() {
+        trace!("Reading {} from {}", url, cached.display());
+        let body = std::fs::read_to_string(&cached)
+            .map_err(|_| crate::smaug::offline_error(url, &cached))?;
+
+        serde_json::from_str(&body).map_err(|err| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Couldn't parse the cached {}: {}", cached.display(), err),
+            )
+        })
+    } else {
+        trace!("Fetching from {}", url);
+        let credentials = crate::credentials::load()?;
+        let request = reqwest::blocking::Client::new().get(url);
+        let response = credentials.authorize_registry(request, REGISTRY_URL).send();
+
+        match response {
+            Err(..) => Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                "couldn't find package",
+            )),
+            Ok(response) => {
+                if !response.status().is_success() {
+                    return Err(io::Error::new(io::ErrorKind::NotFound, not_found()));
+                }
+
+                let body = response
+                    .text()
+                    .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, not_found()))?;
+                let parsed = serde_json::from_str(&body).map_err(|err| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("Couldn't parse the registry response from {}: {}", url, err),
+                    )
+                })?;
+
+                trace!("Caching {} at {}", url, cached.display());
+                std::fs::create_dir_all(cached.parent().unwrap())?;
+                std::fs::write(&cached, &body)?;
+
+                Ok(parsed)
+            }
+        }
+    }
+}
+
+fn cache_path(url: &str) -> PathBuf {
+    let path = url.trim_start_matches(REGISTRY_URL).trim_start_matches('/');
+
+    crate::smaug::cache_dir()
+        .join("registry")
+        .join(format!("{}.json", path))
+}
+
+#[derive(Debug, Default)]
+pub struct Index {
+    releases: HashMap<String, Vec<Release>>,
+    aliases: HashMap<String, String>,
+}
+
+impl Index {
+    /// Looks up `package` in the registry when the solver asks for `name`.
+    pub fn alias(&mut self, name: &str, package: &str) {
+        self.aliases.insert(name.to_string(), package.to_string());
+    }
+}
+
+impl Provider for Index {
+    fn candidates(&mut self, name: &str) -> io::Result<Vec<Candidate>> {
+        let name = self.aliases.get(name).map_or(name, String::as_str);
+
+        if !self.releases.contains_key(name) {
+            let releases = releases(name)?;
+            self.releases.insert(name.to_string(), releases);
+        }
+
+        let candidates = self.releases[name]
+            .iter()
+            .filter_map(|release| {
+                let version = Version::parse(release.version.as_str()).ok()?;
+                let dependencies = release
+                    .dependencies
+                    .iter()
+                    .filter(|(_, requirement)| VersionReq::parse(requirement).is_ok())
+                    .map(|(name, requirement)| (name.clone(), requirement.clone()))
+                    .collect();
+
+                Some(Candidate {
+                    version,
+                    dependencies,
+                })
+            })
+            .collect();
+
+        Ok(candidates)
+    }
+}
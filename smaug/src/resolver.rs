@@ -1,49 +1,490 @@
 use crate::dependency;
+use crate::installed::InstalledPackage;
+use crate::lockfile::{LockedPackage, LockedSource, Lockfile};
+use crate::registry::Index;
+use crate::solver::{Conflict, Error, Requirement, Solution, Solver};
+use crate::source::Fetched;
+use crate::sources::registry_source::RegistrySource;
 use crate::{config, source::Source};
 use config::{Config, DependencyOptions};
 use dependency::Dependency;
+use linked_hash_map::LinkedHashMap;
 use log::*;
+use semver::Version;
 use semver::VersionReq;
 use std::collections::HashMap;
+use std::io;
+use std::path::Path;
 use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
 
 #[derive(Clone, Default)]
 pub struct Resolver {
+    pub name: String,
     pub requirements: Vec<Dependency>,
+    pub dev_requirements: Vec<Dependency>,
     pub source_map: HashMap<String, Box<dyn Source>>,
+    pub options_map: HashMap<String, DependencyOptions>,
+    /// Who first asked for each dependency, to explain source conflicts.
+    pub requested_by: HashMap<String, Requirement>,
+    pub patches: LinkedHashMap<String, DependencyOptions>,
+    pub lock_map: HashMap<String, LockedPackage>,
+    pub dependencies: HashMap<String, Vec<Dependency>>,
+    pub versions: Solution,
+    pub extra_requirements: Vec<(String, Requirement, VersionReq)>,
+    /// Registry requirements of the other members of a workspace, so every
+    /// member is solved to the same versions.
+    pub shared_requirements: Vec<(String, Requirement, VersionReq)>,
     pub installs: Vec<Install>,
     pub requires: Vec<String>,
+    pub dev_requires: Vec<String>,
+    pub package_requires: HashMap<String, Vec<String>>,
+    pub lockfile: Lockfile,
+    pub jobs: usize,
+    pub force: bool,
 }
 
 #[derive(Clone, Debug, Default)]
 pub struct Install {
+    pub package: String,
     pub from: PathBuf,
     pub to: PathBuf,
 }
 
 impl Resolver {
-    pub fn install(&mut self, destination: PathBuf) -> std::io::Result<Vec<Dependency>> {
+    pub fn install(&mut self, destination: PathBuf) -> Result<Vec<Dependency>, Error> {
         info!("Installing Dependencies\n");
 
-        let reqs = self.requirements.clone();
-        let sources = self.source_map.clone();
+        let mut index = Index::default();
+        self.extra_requirements.clear();
 
-        for dependency in reqs.iter() {
-            let source = sources.get(&dependency.name).unwrap();
+        loop {
+            self.solve(&mut index)?;
 
-            if source.installed(dependency, &destination) {
-                info!("{} is already installed", dependency.name);
+            let requirements = self.extra_requirements.len();
+            let installed = self.install_all(&destination)?;
+
+            if self.extra_requirements.len() == requirements {
+                info!("");
+
+                return Ok(installed);
+            }
+
+            debug!("Resolving again with the requirements of installed packages");
+            for package in self.lockfile.packages.iter() {
+                self.lock_map.insert(package.name.clone(), package.clone());
+            }
+        }
+    }
+
+    /// The registry version requirements of the project's own dependencies.
+    pub fn registry_requirements(&self) -> io::Result<Vec<(String, Requirement, VersionReq)>> {
+        let mut requirements = vec![];
+
+        for dependency in self.requirements.iter().chain(self.dev_requirements.iter()) {
+            if let DependencyOptions::Registry { version, .. } = &self.options_map[&dependency.name]
+            {
+                let version_req = VersionReq::parse(version).map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!(
+                            "{} is not a valid version requirement for {}",
+                            version, dependency.name
+                        ),
+                    )
+                })?;
+
+                let requirement = Requirement {
+                    dependent: self.name.clone(),
+                    version: None,
+                    requirement: version.clone(),
+                };
+
+                requirements.push((dependency.name.clone(), requirement, version_req));
+            }
+        }
+
+        Ok(requirements)
+    }
+
+    fn solve(&mut self, index: &mut Index) -> Result<(), Error> {
+        let mut requirements = self.registry_requirements()?;
+
+        requirements.extend(self.extra_requirements.iter().cloned());
+        requirements.extend(
+            self.shared_requirements
+                .iter()
+                .filter(|(_, requirement, _)| requirement.dependent != self.name)
+                .cloned(),
+        );
+
+        if requirements.is_empty() {
+            self.versions = Solution::new();
+            return Ok(());
+        }
+
+        for (name, options) in self.options_map.iter() {
+            if let Some(package) = options.package() {
+                index.alias(name, package);
+            }
+        }
+
+        let mut solver = Solver::new(index);
+
+        for package in self.lock_map.values() {
+            if let LockedSource::Registry { version, .. } = &package.source {
+                if let Ok(version) = Version::parse(version) {
+                    solver.prefer(&package.name, version);
+                }
+            }
+        }
+
+        self.versions = solver.solve(requirements)?;
+        debug!("Resolved versions: {:?}", self.versions);
+
+        Ok(())
+    }
+
+    fn install_all(&mut self, destination: &Path) -> Result<Vec<Dependency>, Error> {
+        let mut installed = vec![];
+        let mut stack = vec![];
+        let mut fetched = HashMap::new();
+        self.lockfile = Lockfile::default();
+        self.dependencies.clear();
+        self.installs.clear();
+        self.requires.clear();
+        self.dev_requires.clear();
+        self.package_requires.clear();
+
+        // Dev-dependencies come last, so anything they install is only
+        // needed by them.
+        for (reqs, dev) in [
+            (self.requirements.clone(), false),
+            (self.dev_requirements.clone(), true),
+        ] {
+            self.prefetch(&reqs, destination, &installed, &mut fetched);
+
+            for dependency in reqs.iter() {
+                self.install_dependency(
+                    dependency,
+                    dev,
+                    destination,
+                    &mut stack,
+                    &mut installed,
+                    &mut fetched,
+                )?;
+            }
+        }
+
+        self.sort_requires()?;
+
+        Ok(installed)
+    }
+
+    /// Orders `requires` so every package is required after the packages it
+    /// depends on.
+    fn sort_requires(&mut self) -> io::Result<()> {
+        let order = topological_order(&self.lockfile.packages)?;
+
+        for package in order {
+            let requires = self
+                .package_requires
+                .get(&package.name)
+                .cloned()
+                .unwrap_or_default();
+
+            if package.dev {
+                self.dev_requires.extend(requires);
             } else {
+                self.requires.extend(requires);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn install_dependency(
+        &mut self,
+        dependency: &Dependency,
+        dev: bool,
+        destination: &Path,
+        stack: &mut Vec<String>,
+        installed: &mut Vec<Dependency>,
+        fetched: &mut HashMap<String, io::Result<Fetched>>,
+    ) -> Result<(), Error> {
+        if stack.contains(&dependency.name) {
+            let mut cycle = stack.clone();
+            cycle.push(dependency.name.clone());
+
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Dependency cycle detected: {}", cycle.join(" -> ")),
+            )
+            .into());
+        }
+
+        if installed.iter().any(|other| other.name == dependency.name) {
+            debug!("{} has already been resolved", dependency.name);
+            return Ok(());
+        }
+
+        let options = self.options_map[&dependency.name].clone();
+        let (pinned, source) = self.select_source(dependency);
+
+        let locked_source = match pinned {
+            Some(package) if !self.force && source.installed(dependency, destination, &package) => {
+                info!("{} is already installed", dependency.name);
+                package.source
+            }
+            _ => {
                 info!("Installing {}", dependency.name);
-                source.install(dependency, &destination)?;
+                let fetched = match fetched.remove(&dependency.name) {
+                    Some(result) => result?,
+                    None => source.fetch(dependency, destination)?,
+                };
+
+                // Check the version before it replaces what's installed. The
+                // solver already picked a matching version for registry
+                // packages.
+                if !options.is_registry() {
+                    if let Some(requirement) = options.version() {
+                        let version = fetched_version(&fetched);
+                        check_version(&dependency.name, &options, requirement, &version)?;
+                    }
+                }
+
+                let locked_source =
+                    crate::source::install_fetched(&fetched, dependency, destination)?;
+
+                match pinned {
+                    None => locked_source,
+                    Some(package) => {
+                        if package.source.revision() != locked_source.revision() {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidData,
+                                format!(
+                                    "{} no longer matches the version in Smaug.lock",
+                                    dependency.name
+                                ),
+                            )
+                            .into());
+                        }
+
+                        package.source
+                    }
+                }
+            }
+        };
+
+        let requires = self.requires.len();
+        source.update_resolver(self, dependency, destination);
+        let requires = self.requires.split_off(requires);
+        self.package_requires
+            .insert(dependency.name.clone(), requires.clone());
+
+        let package_config = crate::config::load(&source.config_path(dependency, destination)).ok();
+        let version = package_config
+            .as_ref()
+            .and_then(|config| config.package.as_ref())
+            .map(|package| package.version.clone())
+            .unwrap_or_default();
+
+        let package_dependencies = match package_config {
+            Some(config) => enabled_dependencies(&dependency.name, config, options.features())?,
+            None => LinkedHashMap::new(),
+        };
+        // Relative paths in a package's dependencies are relative to the
+        // package.
+        let package_dir = match &options {
+            DependencyOptions::Dir { dir, .. } => dir.clone(),
+            _ => PathBuf::from("smaug").join(&dependency.name),
+        };
+        let project_dir = destination.parent().unwrap();
+        let mut children = vec![];
+
+        for (name, child_options) in package_dependencies.iter() {
+            let child_options = child_options.rebased(&package_dir);
+            let requirement = Requirement {
+                dependent: dependency.name.clone(),
+                version: Some(version.clone()),
+                requirement: format!("{}", child_options),
+            };
+            self.check_source(name, &child_options, requirement, project_dir)?;
+            children.push(self.add_dependency(name, &child_options));
+        }
+
+        let package = LockedPackage {
+            name: dependency.name.clone(),
+            version: version.clone(),
+            dependencies: children.iter().map(|child| child.name.clone()).collect(),
+            dev,
+            dependency: options,
+            source: locked_source,
+        };
+        let mut record = InstalledPackage::from(&package);
+        record.requires = requires;
+        record.digest = source.digest(dependency, destination);
+        if let Some(previous) = crate::installed::load(destination, &package.name) {
+            record.files = previous.files;
+        }
+        record.write(destination)?;
+        self.lockfile.packages.push(package);
+        installed.push(dependency.clone());
+
+        self.dependencies
+            .insert(dependency.name.clone(), children.clone());
+
+        self.prefetch(&children, destination, installed, fetched);
+
+        stack.push(dependency.name.clone());
+        for child in children.iter() {
+            let requested = package_dependencies
+                .get(&child.name)
+                .filter(|_| !self.patches.contains_key(&child.name));
+
+            if let Some(DependencyOptions::Registry {
+                version: requirement,
+                ..
+            }) = requested
+            {
+                if let Ok(version_req) = VersionReq::parse(requirement) {
+                    let satisfied = self
+                        .versions
+                        .get(&child.name)
+                        .is_some_and(|solved| version_req.matches(solved));
+
+                    if !satisfied {
+                        debug!(
+                            "{} {} needs {} {}",
+                            dependency.name, version, child.name, requirement
+                        );
+                        let requirement = Requirement {
+                            dependent: dependency.name.clone(),
+                            version: Some(version.clone()),
+                            requirement: requirement.clone(),
+                        };
+                        self.extra_requirements.push((
+                            child.name.clone(),
+                            requirement,
+                            version_req,
+                        ));
+                        continue;
+                    }
+                }
             }
 
-            source.update_resolver(self, dependency, &destination);
+            self.install_dependency(child, dev, destination, stack, installed, fetched)?;
         }
+        stack.pop();
 
-        info!("");
+        Ok(())
+    }
+
+    fn select_source(&self, dependency: &Dependency) -> (Option<LockedPackage>, Box<dyn Source>) {
+        let options = &self.options_map[&dependency.name];
+        let solved = match options {
+            DependencyOptions::Registry { .. } => self.versions.get(&dependency.name).cloned(),
+            _ => None,
+        };
+        let pinned = self
+            .lock_map
+            .get(&dependency.name)
+            .filter(|package| package.dependency == *options)
+            .filter(|package| match (&package.source, &solved) {
+                (LockedSource::Registry { version, .. }, Some(solved)) => {
+                    *version == solved.to_string()
+                }
+                _ => true,
+            })
+            .cloned();
+        let source: Box<dyn Source> = match (&pinned, &solved) {
+            (Some(package), _) => {
+                debug!("Using locked {} {}", package.name, package.version);
+                package.source.to_source()
+            }
+            (None, Some(version)) => Box::new(RegistrySource {
+                version: version.to_string(),
+            }),
+            (None, None) => self.source_map[&dependency.name].clone(),
+        };
 
-        Ok(reqs)
+        (pinned, source)
+    }
+
+    /// Fetches the given dependencies concurrently so that installing them
+    /// afterwards, in order, only has to copy files.
+    fn prefetch(
+        &self,
+        dependencies: &[Dependency],
+        destination: &Path,
+        installed: &[Dependency],
+        fetched: &mut HashMap<String, io::Result<Fetched>>,
+    ) {
+        let mut pending: Vec<(&Dependency, Box<dyn Source>)> = vec![];
+
+        for dependency in dependencies.iter() {
+            let skipped = installed.iter().any(|other| other.name == dependency.name)
+                || fetched.contains_key(&dependency.name)
+                || pending
+                    .iter()
+                    .any(|(other, _)| other.name == dependency.name);
+
+            if skipped {
+                continue;
+            }
+
+            if let DependencyOptions::Registry { .. } = self.options_map[&dependency.name] {
+                if !self.versions.contains_key(&dependency.name) {
+                    continue;
+                }
+            }
+
+            let (pinned, source) = self.select_source(dependency);
+
+            let up_to_date = pinned.as_ref().is_some_and(|package| {
+                !self.force && source.installed(dependency, destination, package)
+            });
+
+            if !up_to_date {
+                pending.push((dependency, source));
+            }
+        }
+
+        if pending.len() < 2 {
+            return;
+        }
+
+        let jobs = match self.jobs {
+            0 => thread::available_parallelism().map_or(1, |jobs| jobs.get()),
+            jobs => jobs,
+        };
+        debug!("Fetching {} packages with {} jobs", pending.len(), jobs);
+
+        let next = AtomicUsize::new(0);
+        let results = Mutex::new(HashMap::new());
+
+        thread::scope(|scope| {
+            for _ in 0..jobs.min(pending.len()) {
+                scope.spawn(|| loop {
+                    let index = next.fetch_add(1, Ordering::SeqCst);
+                    let (dependency, source) = match pending.get(index) {
+                        Some(job) => job,
+                        None => break,
+                    };
+
+                    info!("Fetching {}", dependency.name);
+                    let result = source.fetch(dependency, destination);
+                    results
+                        .lock()
+                        .unwrap()
+                        .insert(dependency.name.clone(), result);
+                });
+            }
+        });
+
+        fetched.extend(results.into_inner().unwrap());
     }
 
     pub fn add_source(&mut self, name: String, source: Box<dyn Source>) {
@@ -53,29 +494,307 @@ impl Resolver {
     pub fn add_requirement(&mut self, dependency: Dependency) {
         self.requirements.push(dependency);
     }
+
+    pub fn add_dev_requirement(&mut self, dependency: Dependency) {
+        self.dev_requirements.push(dependency);
+    }
+
+    /// Fails when `name` was already asked for from somewhere else, since only
+    /// one copy of a package can be installed.
+    fn check_source(
+        &mut self,
+        name: &str,
+        options: &DependencyOptions,
+        requirement: Requirement,
+        project_dir: &Path,
+    ) -> Result<(), Error> {
+        let existing = match self.options_map.get(name) {
+            Some(existing) => existing,
+            None => {
+                self.requested_by.insert(name.to_string(), requirement);
+                return Ok(());
+            }
+        };
+
+        if self
+            .patched(name, options)
+            .same_source(existing, project_dir)
+        {
+            return Ok(());
+        }
+
+        let first = self.requested_by.get(name).cloned().unwrap_or(Requirement {
+            dependent: self.name.clone(),
+            version: None,
+            requirement: format!("{}", existing),
+        });
+
+        Err(Error::SourceConflict(Conflict {
+            package: name.to_string(),
+            requirements: vec![first, requirement],
+        }))
+    }
+
+    fn patched(&self, name: &str, options: &DependencyOptions) -> DependencyOptions {
+        match self.patches.get(name) {
+            Some(patch) => options.patched(patch),
+            None => options.clone(),
+        }
+    }
+
+    pub fn add_dependency(&mut self, name: &str, options: &DependencyOptions) -> Dependency {
+        let options = &self.patched(name, options);
+
+        let version = options
+            .version()
+            .map(str::to_string)
+            .unwrap_or_else(|| VersionReq::any().to_string());
+
+        debug!("{:?}", options);
+        let source =
+            crate::source::from_dependency_options(options).expect("could not create source");
+
+        self.add_source(name.to_string(), source);
+        self.options_map
+            .entry(name.to_string())
+            .or_insert_with(|| options.clone());
+
+        Dependency {
+            name: name.to_string(),
+            version,
+            package: options.package().map(str::to_string),
+        }
+    }
+
+    pub fn use_lockfile(&mut self, lockfile: &Lockfile) {
+        for package in lockfile.packages.iter() {
+            self.lock_map.insert(package.name.clone(), package.clone());
+        }
+    }
+}
+
+/// The version in a fetched package's `Smaug.toml`. Unlike `config::load`,
+/// this leaves the current directory alone.
+fn fetched_version(fetched: &Fetched) -> String {
+    let path = fetched.path.join("Smaug.toml");
+
+    std::fs::read_to_string(&path)
+        .ok()
+        .and_then(|contents| crate::config::from_str(&contents, &path).ok())
+        .and_then(|config| config.package)
+        .map(|package| package.version)
+        .unwrap_or_default()
+}
+
+/// Makes sure a package fetched from somewhere other than the registry is a
+/// version its dependency accepts.
+fn check_version(
+    name: &str,
+    options: &DependencyOptions,
+    requirement: &str,
+    version: &str,
+) -> io::Result<()> {
+    let version_req = VersionReq::parse(requirement).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "{} is not a valid version requirement for {}",
+                requirement, name
+            ),
+        )
+    })?;
+
+    let matches = Version::parse(version).is_ok_and(|version| version_req.matches(&version));
+
+    if matches {
+        return Ok(());
+    }
+
+    let found = if version.is_empty() {
+        "has no version".to_string()
+    } else {
+        format!("is version {}", version)
+    };
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "{} needs to be version {}, but the package at {} {}",
+            name, requirement, options, found
+        ),
+    ))
+}
+
+/// Sorts packages so each one comes after its dependencies. Packages that
+/// don't depend on each other keep the order they were installed in, which
+/// follows the order they're declared in.
+fn topological_order(packages: &[LockedPackage]) -> io::Result<Vec<&LockedPackage>> {
+    let mut remaining: Vec<&LockedPackage> = packages.iter().collect();
+    let mut sorted: Vec<&LockedPackage> = vec![];
+
+    let pending = |remaining: &[&LockedPackage], name: &String| {
+        remaining.iter().any(|package| package.name == *name)
+    };
+
+    while !remaining.is_empty() {
+        let ready = remaining.iter().position(|package| {
+            !package
+                .dependencies
+                .iter()
+                .any(|dependency| pending(&remaining, dependency))
+        });
+
+        match ready {
+            Some(index) => sorted.push(remaining.remove(index)),
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Dependency cycle detected: {}", find_cycle(&remaining)),
+                ))
+            }
+        }
+    }
+
+    Ok(sorted)
+}
+
+/// Follows dependencies from the first package until one repeats. Every
+/// package given must have a dependency among the others.
+fn find_cycle(packages: &[&LockedPackage]) -> String {
+    let mut path: Vec<&str> = vec![];
+    let mut current = packages[0];
+
+    while !path.contains(&current.name.as_str()) {
+        path.push(&current.name);
+        current = current
+            .dependencies
+            .iter()
+            .find_map(|name| packages.iter().find(|package| package.name == *name))
+            .unwrap();
+    }
+
+    let start = path.iter().position(|name| *name == current.name).unwrap();
+    let mut cycle = path.split_off(start);
+    cycle.push(&current.name);
+
+    cycle.join(" -> ")
 }
 
 pub fn new_from_config(config: &Config) -> Resolver {
-    let mut resolver = Resolver::default();
+    let name = match (&config.project, &config.package) {
+        (Some(project), _) => project.name.clone(),
+        (None, Some(package)) => package.name.clone(),
+        (None, None) => "project".to_string(),
+    };
+    let mut resolver = Resolver {
+        name,
+        patches: config.patches(),
+        ..Resolver::default()
+    };
 
     for (name, dependency_options) in config.dependencies.iter() {
-        let name = String::from(name);
-        let version = match dependency_options {
-            DependencyOptions::Registry { version, .. } => version.clone(),
-            _ => VersionReq::any().to_string(),
-        };
+        let dependency = resolver.add_dependency(name, dependency_options);
+        resolver.add_requirement(dependency);
+    }
 
-        debug!("{:?}", dependency_options);
-        let source = crate::source::from_dependency_options(dependency_options)
-            .expect("could not create source");
-        let dependency = Dependency {
-            name: name.clone(),
-            version,
-        };
+    for (name, dependency_options) in config.dev_dependencies.iter() {
+        let dependency = resolver.add_dependency(name, dependency_options);
+        resolver.add_dev_requirement(dependency);
+    }
 
-        resolver.add_requirement(dependency.clone());
-        resolver.add_source(dependency.name, source);
+    for (name, options) in resolver.options_map.iter() {
+        let requirement = Requirement {
+            dependent: resolver.name.clone(),
+            version: None,
+            requirement: format!("{}", options),
+        };
+        resolver.requested_by.insert(name.clone(), requirement);
     }
 
     resolver
 }
+
+/// The dependencies of an installed package, including the optional ones
+/// that come with the features its dependent turned on.
+fn enabled_dependencies(
+    name: &str,
+    config: Config,
+    features: &[String],
+) -> io::Result<LinkedHashMap<String, DependencyOptions>> {
+    let mut dependencies = config.dependencies;
+    let available = config
+        .package
+        .map(|package| package.features)
+        .unwrap_or_default();
+
+    for feature in features.iter() {
+        let enabled = available.get(feature).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{} doesn't have a feature named {}", name, feature),
+            )
+        })?;
+
+        for (child, options) in enabled.dependencies.iter() {
+            dependencies
+                .entry(child.clone())
+                .or_insert_with(|| options.clone());
+        }
+    }
+
+    Ok(dependencies)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, dependencies: &[&str]) -> LockedPackage {
+        let dir = PathBuf::from(format!("../{}", name));
+
+        LockedPackage {
+            name: name.to_string(),
+            version: "1.0.0".to_string(),
+            dependencies: dependencies.iter().map(|name| name.to_string()).collect(),
+            dev: false,
+            dependency: DependencyOptions::Dir {
+                dir: dir.clone(),
+                link: false,
+                version: None,
+                package: None,
+                features: vec![],
+            },
+            source: LockedSource::Dir { dir, link: false },
+        }
+    }
+
+    #[test]
+    fn orders_dependencies_first() {
+        let packages = vec![
+            package("game", &["camera", "tween"]),
+            package("camera", &["tween"]),
+            package("tween", &[]),
+        ];
+
+        let order: Vec<&str> = topological_order(&packages)
+            .unwrap()
+            .iter()
+            .map(|package| package.name.as_str())
+            .collect();
+
+        assert_eq!(order, vec!["tween", "camera", "game"]);
+    }
+
+    #[test]
+    fn reports_dependency_cycles() {
+        let packages = vec![
+            package("c", &["a"]),
+            package("a", &["b"]),
+            package("b", &["a"]),
+        ];
+
+        let err = topological_order(&packages).unwrap_err();
+
+        assert_eq!(err.to_string(), "Dependency cycle detected: a -> b -> a");
+    }
+}
@@ -1,14 +1,109 @@
 use directories::ProjectDirs;
+use log::*;
+use std::env;
+use std::fs;
+use std::io;
+use std::path::Path;
 use std::path::PathBuf;
+use std::process;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+static PARTIALS: AtomicUsize = AtomicUsize::new(0);
+
+#[allow(clippy::needless_return)]
 pub fn data_dir() -> PathBuf {
     return project_dirs().data_dir().to_path_buf();
 }
 
+pub fn config_dir() -> PathBuf {
+    project_dirs().config_dir().to_path_buf()
+}
+
+#[allow(clippy::needless_return)]
 pub fn cache_dir() -> PathBuf {
     return project_dirs().cache_dir().to_path_buf();
 }
 
+pub fn set_offline(offline: bool) {
+    OFFLINE.store(offline, Ordering::SeqCst);
+}
+
+pub fn offline() -> bool {
+    if OFFLINE.load(Ordering::SeqCst) {
+        return true;
+    }
+
+    match env::var("SMAUG_OFFLINE") {
+        Ok(value) => !matches!(value.to_lowercase().as_str(), "" | "0" | "false" | "no"),
+        Err(..) => false,
+    }
+}
+
+/// A temporary path next to `entry` that no other job writes to, not even
+/// parallel jobs in this process filling the same entry.
+pub fn partial_path(entry: &Path) -> PathBuf {
+    let name = entry.file_name().unwrap().to_string_lossy();
+
+    entry.with_file_name(format!(
+        "{}.{}-{}.partial",
+        name,
+        process::id(),
+        PARTIALS.fetch_add(1, Ordering::SeqCst)
+    ))
+}
+
+/// Builds a cache entry in a temporary directory and then moves it into place,
+/// so other projects never see a partially written entry. Existing entries
+/// are reused as they are.
+pub fn fill_cache<F>(entry: &Path, fill: F) -> io::Result<()>
+where
+    F: FnOnce(&Path) -> io::Result<()>,
+{
+    if entry.is_dir() {
+        trace!("Using cached {}", entry.display());
+        return Ok(());
+    }
+
+    let partial = partial_path(entry);
+    remove_dir(&partial)?;
+    fs::create_dir_all(&partial)?;
+
+    if let Err(err) = fill(&partial) {
+        remove_dir(&partial)?;
+        return Err(err);
+    }
+
+    trace!("Caching {}", entry.display());
+    match fs::rename(&partial, entry) {
+        Ok(..) => Ok(()),
+        Err(..) if entry.is_dir() => {
+            debug!("{} was cached by another process", entry.display());
+            remove_dir(&partial)
+        }
+        Err(err) => {
+            remove_dir(&partial)?;
+            Err(err)
+        }
+    }
+}
+
+fn remove_dir(path: &Path) -> io::Result<()> {
+    rm_rf::ensure_removed(path)
+        .map_err(|_| io::Error::other(format!("Couldn't remove {}", path.display())))
+}
+
+pub fn offline_error(artifact: &str, cached: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "Smaug is offline and {} isn't in the cache at {}",
+            artifact,
+            cached.display()
+        ),
+    )
+}
+
 fn project_dirs() -> ProjectDirs {
     ProjectDirs::from("org", "Erebor Studios", "Smaug").expect("No project directories found.")
 }
@@ -0,0 +1,407 @@
+use derive_more::Display;
+use linked_hash_map::LinkedHashMap;
+use log::*;
+use semver::Version;
+use semver::VersionReq;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fmt;
+use std::io;
+
+#[derive(Clone, Debug)]
+pub struct Candidate {
+    pub version: Version,
+    pub dependencies: Vec<(String, String)>,
+}
+
+pub trait Provider {
+    fn candidates(&mut self, name: &str) -> io::Result<Vec<Candidate>>;
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Requirement {
+    pub dependent: String,
+    pub version: Option<String>,
+    pub requirement: String,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Conflict {
+    pub package: String,
+    pub requirements: Vec<Requirement>,
+}
+
+#[derive(Debug, Display)]
+pub enum Error {
+    #[display(fmt = "{}", "_0")]
+    Conflict(Conflict),
+    #[display(fmt = "{}", "_0.sources()")]
+    SourceConflict(Conflict),
+    #[display(fmt = "{}", "_0")]
+    Io(io::Error),
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+pub type Solution = LinkedHashMap<String, Version>;
+
+#[derive(Clone, Default)]
+struct State {
+    assignments: Solution,
+    constraints: HashMap<String, Vec<(Requirement, VersionReq)>>,
+    pending: VecDeque<String>,
+}
+
+pub struct Solver<'a, P: Provider> {
+    provider: &'a mut P,
+    preferred: HashMap<String, Version>,
+    candidates: HashMap<String, Vec<Candidate>>,
+}
+
+impl<'a, P: Provider> Solver<'a, P> {
+    pub fn new(provider: &'a mut P) -> Self {
+        Solver {
+            provider,
+            preferred: HashMap::new(),
+            candidates: HashMap::new(),
+        }
+    }
+
+    pub fn prefer(&mut self, name: &str, version: Version) {
+        self.preferred.insert(name.to_string(), version);
+    }
+
+    pub fn solve(
+        &mut self,
+        requirements: Vec<(String, Requirement, VersionReq)>,
+    ) -> Result<Solution, Error> {
+        let mut state = State::default();
+
+        for (name, requirement, version_req) in requirements {
+            state
+                .constraints
+                .entry(name.clone())
+                .or_default()
+                .push((requirement, version_req));
+            state.pending.push_back(name);
+        }
+
+        self.search(state).map(|state| state.assignments)
+    }
+
+    fn search(&mut self, mut state: State) -> Result<State, Error> {
+        let name = loop {
+            match state.pending.pop_front() {
+                None => return Ok(state),
+                Some(name) if state.assignments.contains_key(&name) => continue,
+                Some(name) => break name,
+            }
+        };
+
+        let constraints = state.constraints.get(&name).cloned().unwrap_or_default();
+        let candidates: Vec<Candidate> = self
+            .candidates(&name)?
+            .into_iter()
+            .filter(|candidate| {
+                constraints
+                    .iter()
+                    .all(|(_, version_req)| version_req.matches(&candidate.version))
+            })
+            .collect();
+
+        let mut conflict = None;
+
+        for candidate in candidates {
+            trace!("Trying {} {}", name, candidate.version);
+            let mut next = state.clone();
+            next.assignments
+                .insert(name.clone(), candidate.version.clone());
+
+            if let Err(found) = next.constrain(&name, &candidate) {
+                conflict.get_or_insert(found);
+                continue;
+            }
+
+            match self.search(next) {
+                Ok(solved) => return Ok(solved),
+                Err(Error::Conflict(found)) => {
+                    conflict.get_or_insert(found);
+                }
+                Err(err) => return Err(err),
+            }
+        }
+
+        Err(Error::Conflict(conflict.unwrap_or_else(|| {
+            Conflict {
+                package: name,
+                requirements: constraints
+                    .into_iter()
+                    .map(|(requirement, _)| requirement)
+                    .collect(),
+            }
+        })))
+    }
+
+    fn candidates(&mut self, name: &str) -> Result<Vec<Candidate>, Error> {
+        if !self.candidates.contains_key(name) {
+            let mut candidates = self.provider.candidates(name)?;
+            candidates.sort_by(|a, b| b.version.cmp(&a.version));
+
+            if let Some(preferred) = self.preferred.get(name) {
+                candidates.sort_by_key(|candidate| candidate.version != *preferred);
+            }
+
+            self.candidates.insert(name.to_string(), candidates);
+        }
+
+        Ok(self.candidates[name].clone())
+    }
+}
+
+impl State {
+    fn constrain(&mut self, name: &str, candidate: &Candidate) -> Result<(), Conflict> {
+        for (dependency, requirement) in candidate.dependencies.iter() {
+            let version_req = match VersionReq::parse(requirement) {
+                Ok(version_req) => version_req,
+                Err(..) => continue,
+            };
+            let requirement = Requirement {
+                dependent: name.to_string(),
+                version: Some(candidate.version.to_string()),
+                requirement: requirement.clone(),
+            };
+
+            let constraints = self.constraints.entry(dependency.clone()).or_default();
+            constraints.push((requirement, version_req.clone()));
+
+            match self.assignments.get(dependency) {
+                Some(assigned) if !version_req.matches(assigned) => {
+                    return Err(Conflict {
+                        package: dependency.clone(),
+                        requirements: constraints
+                            .iter()
+                            .map(|(requirement, _)| requirement.clone())
+                            .collect(),
+                    });
+                }
+                Some(..) => {}
+                None => self.pending.push_back(dependency.clone()),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for Requirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.version {
+            Some(ref version) => write!(f, "{} {}", self.dependent, version),
+            None => write!(f, "{}", self.dependent),
+        }
+    }
+}
+
+impl Conflict {
+    /// Describes a package that was asked for from different places, which
+    /// no choice of version can fix.
+    pub fn sources(&self) -> String {
+        let requirements: Vec<String> = self
+            .requirements
+            .iter()
+            .map(|requirement| {
+                format!(
+                    "{} needs {} from {}",
+                    requirement, self.package, requirement.requirement
+                )
+            })
+            .collect();
+
+        format!(
+            "{} is needed from different sources ({})",
+            self.package,
+            requirements.join(", ")
+        )
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let requirements: Vec<String> = self
+            .requirements
+            .iter()
+            .map(|requirement| {
+                format!(
+                    "{} needs {} {}",
+                    requirement, self.package, requirement.requirement
+                )
+            })
+            .collect();
+
+        write!(
+            f,
+            "No version of {} satisfies every requirement ({})",
+            self.package,
+            requirements.join(", ")
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A package's name, version and the requirements that version has.
+    type Release<'a> = (&'a str, &'a str, &'a [(&'a str, &'a str)]);
+
+    /// Packages by name, each with its versions and what those need.
+    struct Registry(HashMap<String, Vec<Candidate>>);
+
+    impl Registry {
+        fn new(packages: &[Release]) -> Self {
+            let mut registry: HashMap<String, Vec<Candidate>> = HashMap::new();
+
+            for (name, version, dependencies) in packages {
+                registry
+                    .entry(name.to_string())
+                    .or_default()
+                    .push(Candidate {
+                        version: Version::parse(version).unwrap(),
+                        dependencies: dependencies
+                            .iter()
+                            .map(|(name, requirement)| (name.to_string(), requirement.to_string()))
+                            .collect(),
+                    });
+            }
+
+            Registry(registry)
+        }
+    }
+
+    impl Provider for Registry {
+        fn candidates(&mut self, name: &str) -> io::Result<Vec<Candidate>> {
+            Ok(self.0.get(name).cloned().unwrap_or_default())
+        }
+    }
+
+    fn requirement(name: &str, requirement: &str) -> (String, Requirement, VersionReq) {
+        (
+            name.to_string(),
+            Requirement {
+                dependent: "game".to_string(),
+                version: None,
+                requirement: requirement.to_string(),
+            },
+            VersionReq::parse(requirement).unwrap(),
+        )
+    }
+
+    fn versions(solution: &Solution) -> Vec<(&str, String)> {
+        solution
+            .iter()
+            .map(|(name, version)| (name.as_str(), version.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn picks_the_newest_matching_version() {
+        let mut registry = Registry::new(&[
+            ("camera", "1.0.0", &[]),
+            ("camera", "1.2.0", &[]),
+            ("camera", "2.0.0", &[]),
+        ]);
+
+        let solution = Solver::new(&mut registry)
+            .solve(vec![requirement("camera", "^1")])
+            .unwrap();
+
+        assert_eq!(versions(&solution), vec![("camera", "1.2.0".to_string())]);
+    }
+
+    #[test]
+    fn prefers_the_locked_version() {
+        let mut registry = Registry::new(&[("camera", "1.0.0", &[]), ("camera", "1.2.0", &[])]);
+        let mut solver = Solver::new(&mut registry);
+        solver.prefer("camera", Version::parse("1.0.0").unwrap());
+
+        let solution = solver.solve(vec![requirement("camera", "^1")]).unwrap();
+
+        assert_eq!(versions(&solution), vec![("camera", "1.0.0".to_string())]);
+    }
+
+    #[test]
+    fn backtracks_to_an_older_version() {
+        let mut registry = Registry::new(&[
+            ("camera", "1.3.0", &[("tween", "^1")]),
+            ("camera", "2.0.0", &[("tween", "^2")]),
+            ("tween", "1.0.0", &[]),
+            ("tween", "2.0.0", &[]),
+        ]);
+
+        let solution = Solver::new(&mut registry)
+            .solve(vec![requirement("camera", "*"), requirement("tween", "^1")])
+            .unwrap();
+
+        assert_eq!(
+            versions(&solution),
+            vec![
+                ("camera", "1.3.0".to_string()),
+                ("tween", "1.0.0".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_the_requirements_that_conflict() {
+        let mut registry = Registry::new(&[
+            ("camera", "1.3.0", &[("tween", "^1")]),
+            ("tween", "1.0.0", &[]),
+            ("tween", "2.0.0", &[]),
+        ]);
+
+        let err = Solver::new(&mut registry)
+            .solve(vec![
+                requirement("tween", "^2"),
+                requirement("camera", "^1"),
+            ])
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "No version of tween satisfies every requirement \
+             (game needs tween ^2, camera 1.3.0 needs tween ^1)"
+        );
+    }
+
+    #[test]
+    fn describes_different_sources() {
+        let conflict = Conflict {
+            package: "tween".to_string(),
+            requirements: vec![
+                Requirement {
+                    dependent: "game".to_string(),
+                    version: None,
+                    requirement: "../tween".to_string(),
+                },
+                Requirement {
+                    dependent: "camera".to_string(),
+                    version: Some("1.0.0".to_string()),
+                    requirement: "https://github.com/me/tween.git".to_string(),
+                },
+            ],
+        };
+
+        assert_eq!(
+            Error::SourceConflict(conflict).to_string(),
+            "tween is needed from different sources (game needs tween from ../tween, \
+             camera 1.0.0 needs tween from https://github.com/me/tween.git)"
+        );
+    }
+}
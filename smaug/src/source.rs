@@ -1,5 +1,6 @@
 use url_source::UrlSource;
 
+use crate::lockfile::{LockedPackage, LockedSource};
 use crate::resolver::Install;
 use crate::sources::file_source::FileSource;
 use crate::sources::registry_source::RegistrySource;
@@ -8,13 +9,54 @@ use crate::{dependency::Dependency, sources::url_source};
 use crate::{resolver::Resolver, sources::dir_source::DirSource};
 use log::*;
 use std::path::Path;
+use std::path::PathBuf;
 
-pub trait Source: SourceClone {
-    fn install(&self, dependency: &Dependency, path: &Path) -> std::io::Result<()>;
+#[derive(Clone, Debug)]
+pub struct Fetched {
+    pub path: PathBuf,
+    pub source: LockedSource,
+}
 
-    fn installed(&self, dependency: &Dependency, destination: &Path) -> bool {
-        let destination = destination.join(dependency.clone().name);
-        destination.exists()
+pub trait Source: SourceClone + Send + Sync {
+    /// Downloads the package, if needed, and returns the directory holding it.
+    /// Fetches may run concurrently, so they must not touch the project.
+    fn fetch(&self, dependency: &Dependency, destination: &Path) -> std::io::Result<Fetched>;
+
+    fn install(
+        &self,
+        dependency: &Dependency,
+        destination: &Path,
+    ) -> std::io::Result<LockedSource> {
+        let fetched = self.fetch(dependency, destination)?;
+
+        install_fetched(&fetched, dependency, destination)
+    }
+
+    /// The `Smaug.toml` that describes the installed package.
+    fn config_path(&self, dependency: &Dependency, destination: &Path) -> PathBuf {
+        destination.join(&dependency.name).join("Smaug.toml")
+    }
+
+    fn installed(
+        &self,
+        dependency: &Dependency,
+        destination: &Path,
+        locked: &LockedPackage,
+    ) -> bool {
+        if !destination.join(dependency.clone().name).exists() {
+            return false;
+        }
+
+        match crate::installed::load(destination, &dependency.name) {
+            Some(installed) => installed.matches(locked),
+            None => false,
+        }
+    }
+
+    /// A digest of the package's contents, for sources that can change
+    /// without their locked source changing.
+    fn digest(&self, _dependency: &Dependency, _destination: &Path) -> Option<String> {
+        None
     }
 
     fn update_resolver(
@@ -24,17 +66,34 @@ pub trait Source: SourceClone {
         destination: &Path,
     ) {
         let project_dir = destination.parent().unwrap();
+        let config_path = self.config_path(dependency, destination);
         let destination = destination.join(dependency.clone().name);
-        let config_path = destination.join("Smaug.toml");
         let config = crate::config::load(&config_path).expect("Could not find Smaug.toml");
         debug!("Package config: {:?}", config);
         let package = config.package.expect("No package configuration found.");
+        let enabled = resolver
+            .options_map
+            .get(&dependency.name)
+            .map(|options| options.features().to_vec())
+            .unwrap_or_default();
+
+        let mut installs = package.installs;
+        let mut package_requires = package.requires;
+
+        for (name, feature) in package.features {
+            if enabled.contains(&name) {
+                trace!("Enabling feature {} of {}", name, dependency.name);
+                installs.extend(feature.installs);
+                package_requires.extend(feature.requires);
+            }
+        }
 
-        for (from, to) in package.installs {
+        for (from, to) in installs {
             let install_source = from.to_path(destination.as_path());
             let install_destination = to.to_path(project_dir);
 
             let install = Install {
+                package: dependency.name.clone(),
                 from: install_source,
                 to: install_destination,
             };
@@ -42,8 +101,7 @@ pub trait Source: SourceClone {
             resolver.installs.push(install);
         }
 
-        let mut requires = package
-            .requires
+        let mut requires = package_requires
             .iter()
             .map(|require| {
                 let package_file = require.to_path(destination.clone());
@@ -62,6 +120,44 @@ pub trait Source: SourceClone {
     }
 }
 
+pub fn install_fetched(
+    fetched: &Fetched,
+    dependency: &Dependency,
+    destination: &Path,
+) -> std::io::Result<LockedSource> {
+    let destination = destination.join(dependency.clone().name);
+
+    if destination.exists() || crate::util::dir::is_symlink(&destination) {
+        trace!("Removing previous install at {}", destination.display());
+        crate::util::dir::remove(&destination)?;
+    }
+
+    if let LockedSource::Dir { link: true, .. } = fetched.source {
+        let target = dunce::canonicalize(&fetched.path)?;
+        trace!("Linking {} to {}", destination.display(), target.display());
+        std::fs::create_dir_all(destination.parent().unwrap())?;
+
+        match crate::util::dir::symlink_directory(&target, &destination) {
+            Ok(()) => return Ok(fetched.source.clone()),
+            Err(err) => info!(
+                "Couldn't link {} ({}), so it will be copied instead",
+                dependency.name, err
+            ),
+        }
+    }
+
+    trace!(
+        "Installing {} from {} to {}",
+        dependency.name,
+        fetched.path.display(),
+        destination.display()
+    );
+
+    crate::util::dir::copy_directory(&fetched.path, destination)?;
+
+    Ok(fetched.source.clone())
+}
+
 pub trait SourceClone {
     fn clone_box(&self) -> Box<dyn Source>;
 }
@@ -73,22 +169,36 @@ pub fn from_dependency_options(options: &DependencyOptions) -> Option<Box<dyn So
             branch,
             rev,
             tag,
+            path,
+            submodules,
+            ..
         } => Some(Box::new(GitSource {
             repo: repo.clone(),
             branch: branch.clone(),
             rev: rev.clone(),
             tag: tag.clone(),
+            path: path.clone(),
+            submodules: *submodules,
         })),
-        DependencyOptions::Dir { dir: path } => Some(Box::new(DirSource {
+        DependencyOptions::Dir {
+            dir: path, link, ..
+        } => Some(Box::new(DirSource {
             path: path.to_path_buf(),
+            link: *link,
         })),
-        DependencyOptions::File { file: path } => Some(Box::new(FileSource {
+        DependencyOptions::File {
+            file: path,
+            checksum,
+            ..
+        } => Some(Box::new(FileSource {
             path: path.to_path_buf(),
+            checksum: checksum.clone(),
         })),
-        DependencyOptions::Url { url } => Some(Box::new(UrlSource {
+        DependencyOptions::Url { url, checksum, .. } => Some(Box::new(UrlSource {
             url: url.to_string(),
+            checksum: checksum.clone(),
         })),
-        DependencyOptions::Registry { version } => Some(Box::new(RegistrySource {
+        DependencyOptions::Registry { version, .. } => Some(Box::new(RegistrySource {
             version: version.to_string(),
         })),
     }
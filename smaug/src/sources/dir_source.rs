@@ -1,27 +1,70 @@
 use crate::dependency::Dependency;
+use crate::lockfile::LockedPackage;
+use crate::lockfile::LockedSource;
+use crate::source::Fetched;
 use crate::source::Source;
-use log::*;
+use crate::util::dir::is_symlink;
 use std::path::Path;
 use std::path::PathBuf;
 
 #[derive(Clone, Debug)]
 pub struct DirSource {
     pub path: PathBuf,
+    /// Symlink the package instead of copying it, so edits show up right away.
+    pub link: bool,
 }
 
 impl Source for DirSource {
-    fn install(&self, dependency: &Dependency, destination: &Path) -> std::io::Result<()> {
+    fn fetch(&self, _dependency: &Dependency, destination: &Path) -> std::io::Result<Fetched> {
         let project_dir = destination.parent().unwrap();
-        let source = project_dir.join(&self.path);
-        let destination = destination.join(dependency.clone().name);
-        trace!(
-            "Installing directory from {} to {}",
-            source.display(),
-            destination.display()
-        );
 
-        crate::util::dir::copy_directory(&source, destination)?;
+        Ok(Fetched {
+            path: project_dir.join(&self.path),
+            source: LockedSource::Dir {
+                dir: self.path.clone(),
+                link: self.link,
+            },
+        })
+    }
+
+    /// Reads the original so paths in its dependencies stay relative to it.
+    fn config_path(&self, _dependency: &Dependency, destination: &Path) -> PathBuf {
+        let project_dir = destination.parent().unwrap();
+
+        project_dir.join(&self.path).join("Smaug.toml")
+    }
+
+    /// A link is always up to date. A copy is only up to date while the
+    /// directory it was copied from hasn't changed.
+    fn installed(
+        &self,
+        dependency: &Dependency,
+        destination: &Path,
+        locked: &LockedPackage,
+    ) -> bool {
+        let package_dir = destination.join(&dependency.name);
+
+        match crate::installed::load(destination, &dependency.name) {
+            Some(installed) if installed.matches(locked) => {
+                if is_symlink(&package_dir) {
+                    return self.link && package_dir.is_dir();
+                }
+
+                package_dir.is_dir()
+                    && installed.digest.is_some()
+                    && installed.digest == self.digest(dependency, destination)
+            }
+            _ => false,
+        }
+    }
+
+    fn digest(&self, dependency: &Dependency, destination: &Path) -> Option<String> {
+        if is_symlink(&destination.join(&dependency.name)) {
+            return None;
+        }
+
+        let project_dir = destination.parent().unwrap();
 
-        Ok(())
+        crate::util::digest::directory(&project_dir.join(&self.path)).ok()
     }
 }
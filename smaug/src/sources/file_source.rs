@@ -1,6 +1,7 @@
 use crate::dependency::Dependency;
+use crate::lockfile::LockedSource;
+use crate::source::Fetched;
 use crate::source::Source;
-use crate::sources::dir_source::DirSource;
 use log::*;
 use std::path::Path;
 use std::path::PathBuf;
@@ -10,33 +11,53 @@ use zip_extensions::zip_extract;
 #[derive(Clone, Debug)]
 pub struct FileSource {
     pub path: PathBuf,
+    pub checksum: Option<String>,
 }
 
 impl Source for FileSource {
-    fn install(&self, dependency: &Dependency, destination: &Path) -> std::io::Result<()> {
-        trace!("Installing file at {}", self.path.display());
-        let cached = crate::smaug::cache_dir().join(dependency.clone().name);
-
-        rm_rf::ensure_removed(cached.clone()).expect("Couldn't remove directory");
-
-        trace!("Extracting zip to {}", cached.display());
-        zip_extract(&self.path.to_path_buf(), &cached)?;
-
-        trace!(
-            "Finding top level package directory in {}",
-            cached.display()
-        );
-
-        match find_package_dir(&cached) {
-            None => Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                format!("No Smaug.toml file found in {}", cached.display()).as_str(),
-            )),
-            Some(dir) => DirSource { path: dir }.install(dependency, destination),
-        }
+    fn fetch(&self, _dependency: &Dependency, destination: &Path) -> std::io::Result<Fetched> {
+        let project_dir = destination.parent().unwrap();
+        let path = project_dir.join(&self.path);
+        trace!("Installing file at {}", path.display());
+        let digest = crate::util::digest::verify(
+            &path,
+            self.checksum.as_deref(),
+            &self.path.display().to_string(),
+        )?;
+        let cached = crate::smaug::cache_dir().join("file").join(&digest);
+
+        Ok(Fetched {
+            path: extract(&path, &cached)?,
+            source: LockedSource::File {
+                file: self.path.clone(),
+                digest,
+            },
+        })
     }
 }
 
+/// Extracts a package zip into the cache and returns the directory that
+/// contains its Smaug.toml.
+pub fn extract(path: &Path, cached: &Path) -> std::io::Result<PathBuf> {
+    crate::smaug::fill_cache(cached, |partial| {
+        trace!("Extracting zip to {}", partial.display());
+        zip_extract(&path.to_path_buf(), &partial.to_path_buf())
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err.to_string()))
+    })?;
+
+    trace!(
+        "Finding top level package directory in {}",
+        cached.display()
+    );
+
+    find_package_dir(cached).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::NotFound,
+            format!("No Smaug.toml file found in {}", cached.display()).as_str(),
+        )
+    })
+}
+
 fn find_package_dir(path: &Path) -> Option<PathBuf> {
     for entry in WalkDir::new(path) {
         let entry = entry.unwrap();
@@ -1,12 +1,28 @@
+use crate::credentials::Credentials;
 use crate::dependency::Dependency;
+use crate::lockfile::LockedSource;
+use crate::source::Fetched;
 use crate::source::Source;
-use crate::sources::dir_source::DirSource;
 use git2::build::CheckoutBuilder;
-use git2::build::RepoBuilder;
+use git2::AutotagOption;
+use git2::Commit;
 use git2::FetchOptions;
+use git2::ObjectType;
 use git2::Oid;
+use git2::Repository;
 use log::*;
 use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+/// The depth that asks libgit2 for the rest of a shallow repository's
+/// history.
+const UNSHALLOW: i32 = i32::MAX;
+
+/// One lock per repository database, so parallel jobs don't fetch into the
+/// same database at once.
+static DB_LOCKS: Mutex<Vec<(PathBuf, Arc<Mutex<()>>)>> = Mutex::new(Vec::new());
 
 #[derive(Clone, Debug)]
 pub struct GitSource {
@@ -14,74 +30,417 @@ pub struct GitSource {
     pub branch: Option<String>,
     pub rev: Option<String>,
     pub tag: Option<String>,
+    /// The package's directory within the repository.
+    pub path: Option<PathBuf>,
+    pub submodules: bool,
+}
+
+impl GitSource {
+    pub fn remote_revision(&self) -> std::io::Result<String> {
+        let reference = match (&self.tag, &self.branch) {
+            (Some(tag), _) => format!("refs/tags/{}", tag),
+            (None, Some(branch)) => format!("refs/heads/{}", branch),
+            (None, None) => "HEAD".to_string(),
+        };
+        let peeled = format!("{}^{{}}", reference);
+
+        if crate::smaug::offline() {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotConnected,
+                format!("Smaug is offline and can't check {} for updates", self.repo),
+            ));
+        }
+        debug!("Looking up {} in {}", reference, self.repo);
+
+        let not_found = || {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Couldn't find {} in {}", reference, self.repo),
+            )
+        };
+
+        let credentials = crate::credentials::load()?;
+        let mut remote =
+            git2::Remote::create_detached(self.repo.as_str()).map_err(|_| not_found())?;
+        let connection = remote
+            .connect_auth(
+                git2::Direction::Fetch,
+                Some(credentials.remote_callbacks(&self.repo)),
+                None,
+            )
+            .map_err(|_| not_found())?;
+        let heads = connection.list().map_err(|_| not_found())?;
+
+        let head = heads
+            .iter()
+            .find(|head| head.name() == peeled)
+            .or_else(|| heads.iter().find(|head| head.name() == reference))
+            .ok_or_else(not_found)?;
+
+        Ok(head.oid().to_string())
+    }
 }
 
 impl Source for GitSource {
-    fn install(&self, dependency: &Dependency, path: &Path) -> std::io::Result<()> {
-        let destination = crate::smaug::cache_dir().join(dependency.clone().name);
-        trace!(
-            "Installing git repository {} to {}",
-            self.repo,
-            destination.display()
-        );
+    fn fetch(&self, _dependency: &Dependency, _destination: &Path) -> std::io::Result<Fetched> {
+        let (checkout, rev) = self.checkout()?;
+
+        let path = match &self.path {
+            None => checkout,
+            Some(path) => {
+                let package = checkout.join(path);
+
+                if !package.is_dir() {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!(
+                            "Couldn't find {} in revision {} of {}",
+                            path.display(),
+                            rev,
+                            self.repo
+                        ),
+                    ));
+                }
+
+                package
+            }
+        };
+
+        Ok(Fetched {
+            path,
+            source: LockedSource::Git {
+                repo: self.repo.clone(),
+                rev,
+                path: self.path.clone(),
+                submodules: self.submodules,
+            },
+        })
+    }
+}
+
+impl GitSource {
+    /// Checks the requested revision out into the cache, returning where it is
+    /// and which revision it resolved to.
+    fn checkout(&self) -> std::io::Result<(PathBuf, String)> {
+        let cache = crate::smaug::cache_dir()
+            .join("git")
+            .join(crate::util::digest::key(&self.repo));
+        let db_path = cache.join("db");
+
+        let db_lock = db_lock(&db_path);
+        let guard = db_lock.lock().unwrap_or_else(|err| err.into_inner());
+
+        let db = if crate::smaug::offline() {
+            trace!("Opening cached git repository {}", db_path.display());
+            Repository::open_bare(&db_path)
+                .map_err(|_| crate::smaug::offline_error(&self.repo, &db_path))?
+        } else {
+            self.update_db(&db_path)?
+        };
 
-        if destination.exists() {
-            trace!("Removing directory {}", destination.to_str().unwrap());
-            rm_rf::ensure_removed(destination.clone()).unwrap();
+        let oid = self.resolve(&db, &db_path)?;
+        drop(guard);
+        let rev = oid.to_string();
+        debug!("Resolved revision: {}", rev);
+
+        // Submodules change what's checked out, so they're cached separately.
+        let checkout = if self.submodules {
+            cache.join(format!("{}-submodules", rev))
+        } else {
+            cache.join(&rev)
+        };
+
+        crate::smaug::fill_cache(&checkout, |partial| {
+            trace!("Checking out {} to {}", rev, partial.display());
+            let commit = db.find_commit(oid).map_err(git_error)?;
+            let mut builder = CheckoutBuilder::new();
+            builder.target_dir(partial).force();
+            // libgit2 won't check out a tree with submodules in it from a bare
+            // repository. This only changes the open handle, not the database.
+            db.set_workdir(partial, false).map_err(git_error)?;
+
+            db.checkout_tree(commit.as_object(), Some(&mut builder))
+                .map_err(git_error)?;
+
+            if self.submodules {
+                self.checkout_submodules(&commit, partial)?;
+            }
+
+            Ok(())
+        })?;
+
+        Ok((checkout, rev))
+    }
+
+    /// Fills in the submodules listed in `.gitmodules` at the revisions the
+    /// commit points them to, using the same cache as any other repository.
+    fn checkout_submodules(&self, commit: &Commit, dir: &Path) -> std::io::Result<()> {
+        let gitmodules = dir.join(".gitmodules");
+
+        if !gitmodules.is_file() {
+            return Ok(());
+        }
+
+        let config = git2::Config::open(&gitmodules).map_err(git_error)?;
+        let tree = commit.tree().map_err(git_error)?;
+        let mut submodules = vec![];
+
+        let mut entries = config
+            .entries(Some(r"submodule\..*\.path"))
+            .map_err(git_error)?;
+
+        while let Some(entry) = entries.next() {
+            let entry = entry.map_err(git_error)?;
+            let (name, path) = match (entry.name(), entry.value()) {
+                (Some(name), Some(path)) => (name.to_string(), path.to_string()),
+                _ => continue,
+            };
+            let name = &name["submodule.".len()..name.len() - ".path".len()];
+            let url = config
+                .get_string(&format!("submodule.{}.url", name))
+                .map_err(git_error)?;
+
+            submodules.push((path, url));
         }
 
-        let fetch = FetchOptions::new();
-        let checkout = CheckoutBuilder::new();
+        for (path, url) in submodules {
+            let oid = match tree.get_path(Path::new(&path)) {
+                Ok(entry) if entry.kind() == Some(ObjectType::Commit) => entry.id(),
+                _ => {
+                    debug!("{} has no submodule at {}", self.repo, path);
+                    continue;
+                }
+            };
 
-        let mut builder = RepoBuilder::new();
-        builder.fetch_options(fetch);
-        builder.with_checkout(checkout);
+            let submodule = GitSource {
+                repo: submodule_url(&self.repo, &url),
+                branch: None,
+                rev: Some(oid.to_string()),
+                tag: None,
+                path: None,
+                submodules: true,
+            };
+            debug!("Checking out submodule {} from {}", path, submodule.repo);
 
-        debug!("Repository: {}", self.repo);
-        if self.branch.is_some() {
-            debug!("Branch: {}", self.branch.as_ref().unwrap());
-            builder.branch(self.branch.as_ref().unwrap().as_str());
+            let (checkout, _) = submodule.checkout()?;
+            crate::util::dir::copy_directory(&checkout, dir.join(&path))?;
         }
 
-        trace!(
-            "Cloning git repository to {}",
-            destination.to_str().unwrap()
-        );
-        let repository = builder.clone(&self.repo, destination.as_path()).unwrap();
-
-        if self.rev.is_some() {
-            let revision = self.rev.clone().unwrap();
-            debug!("Revision: {}", revision);
-            let mut checkout = CheckoutBuilder::new();
-            let object = repository
-                .find_object(
-                    Oid::from_str(revision.as_str()).expect("Could not find the revision"),
-                    None,
+        Ok(())
+    }
+
+    /// Opens the shared repository database for this URL, fetching the newest
+    /// refs unless the requested revision is already there.
+    fn update_db(&self, db_path: &Path) -> std::io::Result<Repository> {
+        let db = match Repository::open_bare(db_path) {
+            Ok(db) => db,
+            Err(..) => {
+                trace!("Creating git repository {}", db_path.display());
+                std::fs::create_dir_all(db_path)?;
+                Repository::init_bare(db_path).map_err(git_error)?
+            }
+        };
+
+        if let Some(rev) = &self.rev {
+            if db.revparse_single(rev).is_ok() {
+                trace!("{} already has {}", self.repo, rev);
+                return Ok(db);
+            }
+        }
+
+        let credentials = crate::credentials::load()?;
+
+        // Only fetch the newest commit of the ref that was asked for, or the
+        // revision on its own.
+        let shallow = match (&self.rev, &self.tag, &self.branch) {
+            (Some(rev), _, _) if is_full_revision(rev) => {
+                Some(vec![format!("+{0}:refs/revisions/{0}", rev)])
+            }
+            (Some(..), _, _) => None,
+            _ => Some(self.refspecs()),
+        };
+
+        if let Some(refspecs) = shallow {
+            debug!("Fetching {} from {}", refspecs.join(" "), self.repo);
+            match self.fetch_refspecs(&db, &credentials, &refspecs, 1) {
+                Ok(()) if self.has_rev(&db) => return Ok(db),
+                Ok(()) => debug!("{} didn't send the revision", self.repo),
+                Err(err) => debug!("Couldn't fetch a shallow copy: {}", err.message()),
+            }
+        }
+
+        // Short revisions, servers that only serve refs and local repositories
+        // need the full history.
+        let refspecs = self.refspecs();
+        let depth = if db.is_shallow() { UNSHALLOW } else { 0 };
+        debug!("Fetching {} from {}", refspecs.join(" "), self.repo);
+        self.fetch_refspecs(&db, &credentials, &refspecs, depth)
+            .map_err(|err| {
+                std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    format!("Couldn't fetch {}: {}", self.repo, err.message()),
                 )
-                .unwrap();
-            repository
-                .reset(&object, git2::ResetType::Hard, Some(&mut checkout))
-                .unwrap();
+            })?;
+
+        Ok(db)
+    }
+
+    /// The refs to fetch with their full history. A revision could be
+    /// anywhere, so that needs every branch and tag.
+    fn refspecs(&self) -> Vec<String> {
+        match (&self.rev, &self.tag, &self.branch) {
+            (Some(..), _, _) => vec![
+                "+HEAD:refs/remotes/origin/HEAD".to_string(),
+                "+refs/heads/*:refs/remotes/origin/*".to_string(),
+                "+refs/tags/*:refs/tags/*".to_string(),
+            ],
+            (None, Some(tag), _) => vec![format!("+refs/tags/{0}:refs/tags/{0}", tag)],
+            (None, None, Some(branch)) => vec![format!(
+                "+refs/heads/{}:refs/remotes/origin/{}",
+                branch, branch
+            )],
+            (None, None, None) => vec!["+HEAD:refs/remotes/origin/HEAD".to_string()],
+        }
+    }
+
+    fn fetch_refspecs(
+        &self,
+        db: &Repository,
+        credentials: &Credentials,
+        refspecs: &[String],
+        depth: i32,
+    ) -> Result<(), git2::Error> {
+        let mut options = FetchOptions::new();
+        options.remote_callbacks(credentials.remote_callbacks(&self.repo));
+        options.depth(depth);
+        if self.rev.is_none() {
+            options.download_tags(AutotagOption::None);
         }
 
-        if self.tag.is_some() {
-            let tag = self.tag.clone().unwrap();
-            let mut checkout = CheckoutBuilder::new();
+        let mut remote = db.remote_anonymous(&self.repo)?;
+        remote.fetch(refspecs, Some(&mut options), None)
+    }
+
+    fn has_rev(&self, db: &Repository) -> bool {
+        match &self.rev {
+            Some(rev) => db.revparse_single(rev).is_ok(),
+            None => true,
+        }
+    }
+
+    fn resolve(&self, db: &Repository, db_path: &Path) -> std::io::Result<Oid> {
+        let (reference, spec) = match (&self.rev, &self.tag, &self.branch) {
+            (Some(rev), _, _) => (format!("revision {}", rev), rev.clone()),
+            (None, Some(tag), _) => (format!("tag {}", tag), format!("refs/tags/{}", tag)),
+            (None, None, Some(branch)) => (
+                format!("branch {}", branch),
+                format!("refs/remotes/origin/{}", branch),
+            ),
+            (None, None, None) => (
+                "the default branch".to_string(),
+                "refs/remotes/origin/HEAD".to_string(),
+            ),
+        };
+        debug!("Resolving {} of {}", reference, self.repo);
+
+        db.revparse_single(spec.as_str())
+            .and_then(|object| object.peel_to_commit())
+            .map(|commit| commit.id())
+            .map_err(|_| {
+                let artifact = format!("{} of {}", reference, self.repo);
+
+                if crate::smaug::offline() {
+                    crate::smaug::offline_error(artifact.as_str(), db_path)
+                } else {
+                    std::io::Error::new(
+                        std::io::ErrorKind::NotFound,
+                        format!("Couldn't find {}", artifact),
+                    )
+                }
+            })
+    }
+}
+
+/// Resolves a submodule URL like `../other.git` against its parent
+/// repository's URL.
+fn submodule_url(parent: &str, url: &str) -> String {
+    if !url.starts_with("./") && !url.starts_with("../") {
+        return url.to_string();
+    }
 
-            let rev = repository
-                .revparse_single(&tag)
-                .expect("Couldn't parse tag");
+    let mut base = parent.trim_end_matches('/').to_string();
+    let mut rest = url;
 
-            repository
-                .reset(&rev, git2::ResetType::Hard, Some(&mut checkout))
-                .unwrap();
+    loop {
+        if let Some(stripped) = rest.strip_prefix("./") {
+            rest = stripped;
+        } else if let Some(stripped) = rest.strip_prefix("../") {
+            rest = stripped;
+            if let Some(index) = base.rfind('/') {
+                base.truncate(index);
+            }
+        } else {
+            break;
         }
+    }
+
+    format!("{}/{}", base, rest)
+}
+
+/// Whether `rev` is a whole commit id rather than an abbreviation, which is
+/// what a server can send on its own.
+fn is_full_revision(rev: &str) -> bool {
+    rev.len() == 40 && Oid::from_str(rev).is_ok()
+}
+
+fn db_lock(db_path: &Path) -> Arc<Mutex<()>> {
+    let mut locks = DB_LOCKS.lock().unwrap_or_else(|err| err.into_inner());
 
-        let cached = repository.path().parent().expect("No parent dir");
+    match locks.iter().find(|(path, _)| path == db_path) {
+        Some((_, lock)) => lock.clone(),
+        None => {
+            let lock = Arc::new(Mutex::new(()));
+            locks.push((db_path.to_path_buf(), lock.clone()));
 
-        DirSource {
-            path: cached.to_path_buf(),
+            lock
         }
-        .install(dependency, path)
+    }
+}
+
+fn git_error(err: git2::Error) -> std::io::Error {
+    std::io::Error::other(err.message().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_relative_submodule_urls() {
+        let parent = "https://github.com/me/game.git";
+
+        assert_eq!(
+            submodule_url(parent, "../tween.git"),
+            "https://github.com/me/tween.git"
+        );
+        assert_eq!(
+            submodule_url(parent, "./vendor/tween.git"),
+            "https://github.com/me/game.git/vendor/tween.git"
+        );
+        assert_eq!(
+            submodule_url("https://github.com/me/game/", "../../you/tween.git"),
+            "https://github.com/you/tween.git"
+        );
+    }
+
+    #[test]
+    fn keeps_absolute_submodule_urls() {
+        assert_eq!(
+            submodule_url(
+                "https://github.com/me/game.git",
+                "git@github.com:you/tween.git"
+            ),
+            "git@github.com:you/tween.git"
+        );
     }
 }
@@ -1,8 +1,9 @@
 use crate::dependency::Dependency;
+use crate::lockfile::LockedSource;
+use crate::source::Fetched;
 use crate::source::Source;
 use crate::sources::git_source::GitSource;
 use log::*;
-use serde::Deserialize;
 use std::path::Path;
 
 #[derive(Clone, Debug)]
@@ -10,74 +11,37 @@ pub struct RegistrySource {
     pub version: String,
 }
 
-#[derive(Debug, Deserialize)]
-struct RepositoryResponse {
-    url: String,
-    tag: String,
-}
-
-#[derive(Debug, Deserialize)]
-struct VersionResponse {
-    repository: RepositoryResponse,
-}
-
-#[derive(Debug, Deserialize)]
-struct PackageResponse {
-    version: VersionResponse,
-}
-
 impl Source for RegistrySource {
-    fn install(&self, dependency: &Dependency, destination: &Path) -> std::io::Result<()> {
+    fn fetch(&self, dependency: &Dependency, destination: &Path) -> std::io::Result<Fetched> {
         trace!(
             "Fetching {} version {} from registry",
-            dependency.clone().name,
+            dependency.package(),
             self.version
         );
 
-        let source = fetch_from_registry(dependency.name.clone(), self.version.clone())?;
-
-        source.install(dependency, destination)
-    }
-}
-
-fn fetch_from_registry(name: String, version: String) -> std::io::Result<GitSource> {
-    let url = format!(
-        "https://api.smaug.dev/packages/{}/versions/{}",
-        name, version
-    );
-    trace!("Fetching from {}", url);
-    let response = reqwest::blocking::get(url.as_str());
-
-    match response {
-        Err(..) => Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            "couldn't find package",
-        )),
-        Ok(response) => parse_response(response, name, version),
-    }
-}
-
-fn parse_response(
-    response: reqwest::blocking::Response,
-    name: String,
-    version: String,
-) -> std::io::Result<GitSource> {
-    if response.status().is_success() {
-        let package_response: PackageResponse =
-            response.json().expect("Couldn't parse registry response");
-        Ok(GitSource {
-            repo: package_response.version.repository.url,
-            tag: Some(package_response.version.repository.tag),
+        let release = crate::registry::release(dependency.package(), &self.version)?;
+        let source = GitSource {
+            repo: release.repository.url,
+            tag: Some(release.repository.tag.clone()),
             rev: None,
             branch: None,
-        })
-    } else {
-        Err(std::io::Error::new(
-            std::io::ErrorKind::NotFound,
-            format!(
-                "Couldn't fetch {} version {} from repository",
-                name, version
-            ),
-        ))
+            path: None,
+            submodules: false,
+        };
+
+        let fetched = source.fetch(dependency, destination)?;
+
+        match fetched.source {
+            LockedSource::Git { repo, rev, .. } => Ok(Fetched {
+                path: fetched.path,
+                source: LockedSource::Registry {
+                    version: release.version,
+                    repo,
+                    tag: release.repository.tag,
+                    rev,
+                },
+            }),
+            _ => unreachable!(),
+        }
     }
 }
@@ -1,6 +1,7 @@
 use crate::dependency::Dependency;
+use crate::lockfile::LockedSource;
+use crate::source::Fetched;
 use crate::source::Source;
-use crate::sources::file_source::FileSource;
 use log::*;
 use std::fs::File;
 use std::path::Path;
@@ -8,32 +9,118 @@ use std::path::Path;
 #[derive(Clone, Debug)]
 pub struct UrlSource {
     pub url: String,
+    pub checksum: Option<String>,
 }
 
 impl Source for UrlSource {
-    fn install(&self, dependency: &Dependency, destination: &Path) -> std::io::Result<()> {
-        trace!("Downloading Url from {}", self.url);
-        let file_name = format!("{}.zip", dependency.clone().name);
-        let cached = crate::smaug::cache_dir().join(file_name);
+    fn fetch(&self, _dependency: &Dependency, _destination: &Path) -> std::io::Result<Fetched> {
+        let cache = crate::smaug::cache_dir().join("url");
+        let index = cache
+            .join("index")
+            .join(crate::util::digest::key(&self.url));
+
+        let expected = match &self.checksum {
+            Some(checksum) => Some(crate::util::digest::expected_sha256(checksum)?.to_lowercase()),
+            None => None,
+        };
+        let zip_path = |digest: &str| cache.join(format!("{}.zip", digest));
+
+        let digest = match (&expected, crate::smaug::offline()) {
+            (Some(digest), _) if zip_path(digest).is_file() => {
+                trace!("Using cached download {}", zip_path(digest).display());
+                digest.clone()
+            }
+            (Some(digest), true) => {
+                return Err(crate::smaug::offline_error(&self.url, &zip_path(digest)))
+            }
+            (None, true) => {
+                trace!("Looking up the cached download of {}", self.url);
+                std::fs::read_to_string(&index)
+                    .map(|digest| digest.trim().to_string())
+                    .map_err(|_| crate::smaug::offline_error(&self.url, &index))?
+            }
+            (_, false) => self.download(&cache, &index)?,
+        };
 
-        if cached.exists() {
-            std::fs::remove_file(cached.clone())?;
+        let zip = zip_path(&digest);
+
+        if !zip.is_file() {
+            return Err(crate::smaug::offline_error(&self.url, &zip));
         }
 
-        trace!("Downloading package to {}", cached.display());
-        std::fs::create_dir_all(cached.parent().unwrap())?;
-        let mut file = File::create(cached.clone())?;
-        let response = reqwest::blocking::get(self.url.as_str());
-
-        match response {
-            Err(..) => Err(std::io::Error::new(
-                std::io::ErrorKind::NotFound,
-                "Couldn't download file.",
-            )),
-            Ok(mut response) => {
-                std::io::copy(&mut response, &mut file)?;
-                FileSource { path: cached }.install(dependency, destination)
+        Ok(Fetched {
+            path: crate::sources::file_source::extract(&zip, &cache.join(&digest))?,
+            source: LockedSource::Url {
+                url: self.url.clone(),
+                digest,
+            },
+        })
+    }
+}
+
+impl UrlSource {
+    /// Downloads the zip into the cache, named by its digest, and records
+    /// which digest the URL served last so it can be found offline.
+    fn download(&self, cache: &Path, index: &Path) -> std::io::Result<String> {
+        trace!("Downloading Url from {}", self.url);
+        let partial = crate::smaug::partial_path(&cache.join(crate::util::digest::key(&self.url)));
+
+        trace!("Downloading package to {}", partial.display());
+        std::fs::create_dir_all(cache)?;
+        let mut file = File::create(&partial)?;
+        let credentials = crate::credentials::load()?;
+        let request = reqwest::blocking::Client::new().get(self.url.as_str());
+        let response = credentials.authorize(request, &self.url).send();
+
+        let mut response = match response {
+            Err(..) => {
+                std::fs::remove_file(&partial)?;
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::NotFound,
+                    "Couldn't download file.",
+                ));
+            }
+            Ok(response) if !response.status().is_success() => {
+                std::fs::remove_file(&partial)?;
+                return Err(download_error(&self.url, response.status()));
             }
+            Ok(response) => response,
+        };
+
+        std::io::copy(&mut response, &mut file)?;
+        let digest =
+            match crate::util::digest::verify(&partial, self.checksum.as_deref(), &self.url) {
+                Ok(digest) => digest,
+                Err(err) => {
+                    std::fs::remove_file(&partial)?;
+                    return Err(err);
+                }
+            };
+        let zip = cache.join(format!("{}.zip", digest));
+
+        if zip.is_file() {
+            std::fs::remove_file(&partial)?;
+        } else {
+            std::fs::rename(&partial, &zip)?;
         }
+
+        std::fs::create_dir_all(index.parent().unwrap())?;
+        std::fs::write(index, &digest)?;
+
+        Ok(digest)
     }
 }
+
+fn download_error(url: &str, status: reqwest::StatusCode) -> std::io::Error {
+    let message = match status {
+        reqwest::StatusCode::UNAUTHORIZED | reqwest::StatusCode::FORBIDDEN => format!(
+            "Couldn't download {} ({}). Add a token for it to {}",
+            url,
+            status,
+            crate::credentials::path().display()
+        ),
+        _ => format!("Couldn't download {} ({})", url, status),
+    };
+
+    std::io::Error::new(std::io::ErrorKind::NotFound, message)
+}
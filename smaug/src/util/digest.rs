@@ -1,4 +1,5 @@
 use blake2::{Blake2b, Digest};
+use sha2::Sha256;
 use std::path::Path;
 use std::{fs, io};
 
@@ -10,3 +11,79 @@ pub fn file(path: &Path) -> io::Result<String> {
 
     Ok(format!("{:x}", hash))
 }
+
+pub fn sha256(path: &Path) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    let hash = hasher.finalize();
+
+    Ok(format!("{:x}", hash))
+}
+
+/// Hashes the files `copy_directory` would copy from `path`, along with their
+/// paths, so renaming, adding or editing a file changes the digest.
+pub fn directory(path: &Path) -> io::Result<String> {
+    let mut hasher = Blake2b::new();
+
+    for file in crate::util::dir::files(path)? {
+        let relative = file.strip_prefix(path).unwrap();
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        hasher.update((relative.len() as u64).to_le_bytes());
+        hasher.update(relative.as_bytes());
+        hasher.update(fs::metadata(&file)?.len().to_le_bytes());
+        io::copy(&mut fs::File::open(&file)?, &mut hasher)?;
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// A short, filesystem safe key for a string such as a repository URL.
+pub fn key(value: &str) -> String {
+    let hash = Blake2b::digest(value.as_bytes());
+
+    format!("{:x}", hash)[..16].to_string()
+}
+
+/// Formats a SHA-256 digest the way it's written in Smaug.toml.
+pub fn checksum(digest: &str) -> String {
+    format!("sha256:{}", digest)
+}
+
+/// Hashes the file and, when a `sha256:<hex>` checksum is given, makes sure
+/// the file matches it. Returns the file's SHA-256 digest.
+pub fn verify(path: &Path, checksum: Option<&str>, name: &str) -> io::Result<String> {
+    let digest = sha256(path)?;
+
+    if let Some(checksum) = checksum {
+        let expected = expected_sha256(checksum)?;
+
+        if !expected.eq_ignore_ascii_case(&digest) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Checksum mismatch for {}: expected sha256:{}, found sha256:{}",
+                    name,
+                    expected.to_lowercase(),
+                    digest
+                ),
+            ));
+        }
+    }
+
+    Ok(digest)
+}
+
+/// Returns the hex digest of a `sha256:<hex>` checksum.
+pub fn expected_sha256(checksum: &str) -> io::Result<&str> {
+    match checksum.strip_prefix("sha256:") {
+        Some(hex) if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Ok(hex),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "{} is not a valid checksum. Use sha256: followed by the hex digest.",
+                checksum
+            ),
+        )),
+    }
+}
@@ -2,12 +2,38 @@ use ignore::gitignore::{Gitignore, GitignoreBuilder};
 use log::*;
 use std::fs;
 use std::io;
+use std::path::Component;
 use std::path::Path;
+use std::path::PathBuf;
 use walkdir::WalkDir;
 
 pub fn copy_directory<P: AsRef<Path>>(source: &P, destination: P) -> io::Result<()> {
+    for entry in files(source.as_ref())? {
+        let relative = entry.strip_prefix(source.as_ref()).unwrap();
+        let new_path = destination.as_ref().join(relative);
+
+        trace!(
+            "Creating directory {}",
+            new_path.parent().and_then(|p| p.to_str()).unwrap()
+        );
+        fs::create_dir_all(new_path.parent().unwrap())?;
+        trace!(
+            "Copying file from {} to {}",
+            entry.to_str().unwrap(),
+            new_path.to_str().unwrap()
+        );
+        fs::copy(entry, new_path)?;
+    }
+
+    Ok(())
+}
+
+/// The files `copy_directory` copies: everything under `source` that isn't
+/// ignored by its `.smaugignore` or part of a git repository, following
+/// links to linked packages. Sorted, so the list is stable.
+pub fn files(source: &Path) -> io::Result<Vec<PathBuf>> {
     let mut ignore_builder = GitignoreBuilder::new(source);
-    let ignore_file = source.as_ref().join(".smaugignore");
+    let ignore_file = source.join(".smaugignore");
 
     if ignore_file.is_file() {
         ignore_builder.add(ignore_file);
@@ -17,28 +43,83 @@ pub fn copy_directory<P: AsRef<Path>>(source: &P, destination: P) -> io::Result<
         .build()
         .expect("Could not parse smaugignore file");
 
-    for entry in WalkDir::new(source) {
-        let entry = entry.expect("Could not find directory");
+    let mut files = vec![];
+
+    for entry in WalkDir::new(source)
+        .follow_links(true)
+        .sort_by(|a, b| a.file_name().cmp(b.file_name()))
+    {
+        let entry = entry?;
         let entry = entry.path();
-        let relative = entry.strip_prefix(source.as_ref()).unwrap();
-        let new_path = destination.as_ref().join(relative);
 
         if entry.is_file() && !is_ignored(entry, &ignore) {
-            trace!(
-                "Creating directory {}",
-                new_path.parent().and_then(|p| p.to_str()).unwrap()
-            );
-            fs::create_dir_all(new_path.parent().unwrap())?;
-            trace!(
-                "Copying file from {} to {}",
-                entry.to_str().unwrap(),
-                new_path.to_str().unwrap()
-            );
-            fs::copy(entry, new_path)?;
+            files.push(entry.to_path_buf());
         }
     }
 
-    Ok(())
+    Ok(files)
+}
+
+/// Makes `link` point at the directory `target`.
+#[cfg(unix)]
+pub fn symlink_directory(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+/// Makes `link` point at the directory `target`.
+#[cfg(windows)]
+pub fn symlink_directory(target: &Path, link: &Path) -> io::Result<()> {
+    std::os::windows::fs::symlink_dir(target, link)
+}
+
+/// Makes `link` point at the directory `target`.
+#[cfg(not(any(unix, windows)))]
+pub fn symlink_directory(_target: &Path, _link: &Path) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "symlinks aren't supported on this platform",
+    ))
+}
+
+pub fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+/// Removes a directory. A symlink is unlinked, leaving the directory it points
+/// at alone.
+pub fn remove(path: &Path) -> io::Result<()> {
+    if is_symlink(path) {
+        return fs::remove_file(path).or_else(|_| fs::remove_dir(path));
+    }
+
+    rm_rf::ensure_removed(path)
+        .map_err(|_| io::Error::other(format!("Couldn't remove {}", path.display())))
+}
+
+/// Resolves `.` and `..` in `path` without reading the file system. A `..`
+/// that climbs above where the path starts is kept.
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => match normalized.components().next_back() {
+                Some(Component::Normal(..)) => {
+                    normalized.pop();
+                }
+                Some(Component::RootDir) | Some(Component::Prefix(..)) => {}
+                _ => normalized.push(".."),
+            },
+            component => normalized.push(component),
+        }
+    }
+
+    if normalized.as_os_str().is_empty() {
+        normalized.push(".");
+    }
+
+    normalized
 }
 
 fn is_git_dir(path: &str) -> bool {
@@ -0,0 +1,207 @@
+use crate::config::DependencyOptions;
+use crate::lockfile::Lockfile;
+use linked_hash_map::LinkedHashMap;
+use log::*;
+use serde::Deserialize;
+use serde::Serialize;
+use std::io;
+use std::path::Path;
+use std::path::PathBuf;
+
+/// Games and packages that live in one repository and share a single
+/// `Smaug.lock`, declared by a root `Smaug.toml` with a `[workspace]` table.
+#[derive(Clone, Debug)]
+pub struct Workspace {
+    pub root: PathBuf,
+    pub members: Vec<Member>,
+    pub patch: LinkedHashMap<String, DependencyOptions>,
+    pub local_patch: LinkedHashMap<String, DependencyOptions>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct Member {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    workspace: Option<WorkspaceTable>,
+    #[serde(default)]
+    patch: LinkedHashMap<String, DependencyOptions>,
+}
+
+#[derive(Debug, Deserialize)]
+struct WorkspaceTable {
+    #[serde(default)]
+    members: Vec<PathBuf>,
+}
+
+/// Loads the workspace whose root is `root`, if its `Smaug.toml` declares one.
+pub fn load(root: &Path) -> io::Result<Option<Workspace>> {
+    let config_path = root.join("Smaug.toml");
+
+    if !config_path.is_file() {
+        return Ok(None);
+    }
+
+    let contents = std::fs::read_to_string(&config_path)?;
+    let table = contents
+        .parse::<toml::Value>()
+        .map_err(|err| invalid(&config_path, err))?;
+
+    if table.get("workspace").is_none() {
+        return Ok(None);
+    }
+
+    // Patches written as plain paths are resolved from the current directory.
+    std::env::set_current_dir(root)?;
+    let manifest: Manifest = toml::from_str(&contents).map_err(|err| invalid(&config_path, err))?;
+    let members = manifest
+        .workspace
+        .map(|workspace| workspace.members)
+        .unwrap_or_default()
+        .iter()
+        .map(|member| load_member(root, member))
+        .collect::<io::Result<Vec<Member>>>()?;
+
+    let local_patch = crate::config::local_patch(&config_path)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.to_string()))?;
+
+    Ok(Some(Workspace {
+        root: root.to_path_buf(),
+        members,
+        patch: manifest.patch,
+        local_patch,
+    }))
+}
+
+/// Finds the workspace that `path` is the root or a member of.
+pub fn find(path: &Path) -> io::Result<Option<Workspace>> {
+    for root in path.ancestors() {
+        if !root.join("Smaug.toml").is_file() {
+            continue;
+        }
+
+        if let Some(workspace) = load(root)? {
+            if workspace.root == path || workspace.member_at(path).is_some() {
+                return Ok(Some(workspace));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+impl Workspace {
+    pub fn lock_path(&self) -> PathBuf {
+        self.root.join("Smaug.lock")
+    }
+
+    /// Finds a member by its name or its path relative to the root.
+    pub fn member(&self, name: &str) -> Option<&Member> {
+        self.members
+            .iter()
+            .find(|member| member.name == name)
+            .or_else(|| {
+                dunce::canonicalize(self.root.join(name))
+                    .ok()
+                    .and_then(|path| self.member_at(&path))
+            })
+    }
+
+    pub fn member_at(&self, path: &Path) -> Option<&Member> {
+        self.members.iter().find(|member| member.path == path)
+    }
+
+    /// Makes patches written relative to the root relative to `member`
+    /// instead, so they keep working from the member without locking an
+    /// absolute path.
+    pub fn member_patch(
+        &self,
+        member: &Member,
+        patch: &LinkedHashMap<String, DependencyOptions>,
+    ) -> LinkedHashMap<String, DependencyOptions> {
+        let root = self.root_from(member);
+
+        patch
+            .iter()
+            .map(|(name, options)| (name.clone(), options.rebased(&root)))
+            .collect()
+    }
+
+    /// Makes the paths a member locked relative to the root instead, so the
+    /// shared Smaug.lock reads the same whichever member locked a package.
+    pub fn root_lockfile(&self, member: &Member, lockfile: &Lockfile) -> Lockfile {
+        let member_dir = match member.path.strip_prefix(&self.root) {
+            Ok(relative) => relative.to_path_buf(),
+            Err(..) => member.path.clone(),
+        };
+
+        relocated(lockfile, &member_dir)
+    }
+
+    /// Makes the paths in the shared Smaug.lock relative to `member`, which
+    /// is where it reads them from.
+    pub fn member_lockfile(&self, member: &Member, lockfile: &Lockfile) -> Lockfile {
+        relocated(lockfile, &self.root_from(member))
+    }
+
+    /// The root as seen from `member`.
+    fn root_from(&self, member: &Member) -> PathBuf {
+        match member.path.strip_prefix(&self.root) {
+            Ok(relative) => relative.components().map(|_| Path::new("..")).collect(),
+            Err(..) => self.root.clone(),
+        }
+    }
+}
+
+fn relocated(lockfile: &Lockfile, base: &Path) -> Lockfile {
+    Lockfile {
+        packages: lockfile
+            .packages
+            .iter()
+            .map(|package| package.relocated(base))
+            .collect(),
+    }
+}
+
+fn load_member(root: &Path, member: &Path) -> io::Result<Member> {
+    let path = dunce::canonicalize(root.join(member)).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!(
+                "Workspace member {} doesn't exist in {}",
+                member.display(),
+                root.display()
+            ),
+        )
+    })?;
+
+    let config_path = path.join("Smaug.toml");
+    let contents = std::fs::read_to_string(&config_path).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Could not find Smaug.toml at {}", config_path.display()),
+        )
+    })?;
+    let config = contents
+        .parse::<toml::Value>()
+        .map_err(|err| invalid(&config_path, err))?;
+
+    let name = ["project", "package"]
+        .iter()
+        .find_map(|table| config.get(table)?.get("name")?.as_str())
+        .map(str::to_string)
+        .unwrap_or_else(|| path.file_name().unwrap().to_string_lossy().into_owned());
+    debug!("Workspace member {} at {}", name, path.display());
+
+    Ok(Member { name, path })
+}
+
+fn invalid<E: std::fmt::Display>(path: &Path, err: E) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Could not parse {}: {}", path.display(), err),
+    )
+}